@@ -0,0 +1,92 @@
+//! Measure query planning time for an operation whose subgraph fetch reuses named fragments,
+//! which exercises the fragment reduction fix-point loop.
+//!
+//! Run with `cargo bench --bench reuse_fragments`
+
+use std::time::Instant;
+
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::query_plan::query_planner::QueryPlanner;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::Supergraph;
+
+const SUPERGRAPH: &str =
+    include_str!("../tests/query_plan/supergraphs/it_works_with_nested_fragments_1.graphql");
+
+const OPERATION: &str = r#"
+  query {
+    a {
+      ... on A1 {
+        ...FooSelect
+      }
+      ... on A2 {
+        ...FooSelect
+      }
+      ... on A3 {
+        ...FooSelect
+      }
+    }
+  }
+
+  fragment FooSelect on Foo {
+    __typename
+    foo
+    child {
+      ...FooChildSelect
+    }
+    child2 {
+      ...FooChildSelect
+    }
+  }
+
+  fragment FooChildSelect on Foo {
+    __typename
+    foo
+    child {
+      child {
+        child {
+          foo
+        }
+      }
+    }
+  }
+"#;
+
+const ITERATIONS: u32 = 1_000;
+
+fn main() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let planner = QueryPlanner::new(
+        &supergraph,
+        QueryPlannerConfig {
+            reuse_query_fragments: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let document = ExecutableDocument::parse_and_validate(
+        planner.api_schema().schema(),
+        OPERATION,
+        "operation.graphql",
+    )
+    .unwrap();
+
+    // Warm up
+    for _ in 0..10 {
+        planner
+            .build_query_plan(&document, None, Default::default())
+            .unwrap();
+    }
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        planner
+            .build_query_plan(&document, None, Default::default())
+            .unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{ITERATIONS} query plans in {elapsed:?} ({:?} per plan)",
+        elapsed / ITERATIONS
+    );
+}
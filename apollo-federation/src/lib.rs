@@ -54,6 +54,13 @@ use crate::link::spec::Identity;
 use crate::link::spec_definition::SpecDefinitions;
 use crate::merge::merge_subgraphs;
 use crate::merge::MergeFailure;
+pub use crate::operation::FragmentNaming;
+pub use crate::operation::NamedFragments;
+pub use crate::operation::Operation;
+pub use crate::operation::OptimizeMode;
+pub use crate::operation::OptimizeOptions;
+pub use crate::operation::OptimizeStats;
+pub use crate::operation::SelectionSet;
 use crate::schema::ValidFederationSchema;
 use crate::subgraph::ValidSubgraph;
 pub use crate::supergraph::ValidFederationSubgraph;
@@ -135,9 +142,16 @@ impl Supergraph {
         api_schema::to_api_schema(self.schema.clone(), options)
     }
 
+    /// Extracts all the subgraphs of this supergraph. The returned subgraphs are ordered by
+    /// subgraph name.
     pub fn extract_subgraphs(&self) -> Result<ValidFederationSubgraphs, FederationError> {
         supergraph::extract_subgraphs_from_supergraph(&self.schema, None)
     }
+
+    /// Extracts the subgraph named `name`, without completing and validating the other subgraphs.
+    pub fn extract_subgraph(&self, name: &str) -> Result<ValidFederationSubgraph, FederationError> {
+        supergraph::extract_subgraph_from_supergraph(&self.schema, name, None)
+    }
 }
 
 const _: () = {
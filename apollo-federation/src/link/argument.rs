@@ -79,8 +79,81 @@ pub(crate) fn directive_required_string_argument<'doc>(
     })
 }
 
+/// Reads a list of strings argument. Like GraphQL input coercion, a single string is accepted as a
+/// list containing only that string.
+pub(crate) fn directive_optional_string_list_argument(
+    application: &Directive,
+    name: &Name,
+) -> Result<Option<Vec<String>>, FederationError> {
+    let invalid = || -> FederationError {
+        SingleFederationError::InvalidFederationSupergraph {
+            message: format!(
+                "Argument \"{}\" of directive \"@{}\" must be a list of strings.",
+                name, application.name
+            ),
+        }
+        .into()
+    };
+    match application.specified_argument_by_name(name) {
+        Some(value) => match value.deref() {
+            Value::List(values) => values
+                .iter()
+                .map(|value| value.as_str().map(str::to_string).ok_or_else(invalid))
+                .collect::<Result<_, _>>()
+                .map(Some),
+            Value::String(_) => Ok(value.as_str().map(|value| vec![value.to_string()])),
+            Value::Null => Ok(None),
+            _ => Err(invalid()),
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn directive_optional_int_argument(
+    application: &Directive,
+    name: &Name,
+) -> Result<Option<i32>, FederationError> {
+    match application.specified_argument_by_name(name) {
+        Some(value) => match value.deref() {
+            Value::Int(value) => value.try_to_i32().map(Some).map_err(|_| {
+                SingleFederationError::InvalidFederationSupergraph {
+                    message: format!(
+                        "Argument \"{}\" of directive \"@{}\" must be a 32-bit integer.",
+                        name, application.name
+                    ),
+                }
+                .into()
+            }),
+            Value::Null => Ok(None),
+            _ => Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Argument \"{}\" of directive \"@{}\" must be an integer.",
+                    name, application.name
+                ),
+            }
+            .into()),
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn directive_required_int_argument(
+    application: &Directive,
+    name: &Name,
+) -> Result<i32, FederationError> {
+    directive_optional_int_argument(application, name)?.ok_or_else(|| {
+        SingleFederationError::InvalidFederationSupergraph {
+            message: format!(
+                "Required argument \"{}\" of directive \"@{}\" was not present.",
+                name, application.name
+            ),
+        }
+        .into()
+    })
+}
+
 pub(crate) fn directive_optional_boolean_argument(
-    application: &Node<Directive>,
+    application: &Directive,
     name: &Name,
 ) -> Result<Option<bool>, FederationError> {
     match application.specified_argument_by_name(name) {
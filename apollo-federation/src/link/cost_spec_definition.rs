@@ -1,9 +1,12 @@
+use std::fmt::Display;
+
 use apollo_compiler::ast::Argument;
 use apollo_compiler::ast::Directive;
 use apollo_compiler::collections::IndexMap;
 use apollo_compiler::name;
 use apollo_compiler::schema::Component;
 use apollo_compiler::schema::EnumType;
+use apollo_compiler::schema::ExtendedType;
 use apollo_compiler::schema::ObjectType;
 use apollo_compiler::schema::ScalarType;
 use apollo_compiler::Name;
@@ -11,21 +14,33 @@ use apollo_compiler::Node;
 use lazy_static::lazy_static;
 
 use crate::error::FederationError;
+use crate::error::SingleFederationError;
+use crate::link::argument::directive_optional_boolean_argument;
+use crate::link::argument::directive_optional_int_argument;
+use crate::link::argument::directive_optional_string_list_argument;
+use crate::link::argument::directive_required_int_argument;
 use crate::link::spec::Identity;
 use crate::link::spec::Url;
 use crate::link::spec::Version;
 use crate::link::spec_definition::SpecDefinition;
 use crate::link::spec_definition::SpecDefinitions;
 use crate::schema::position::EnumTypeDefinitionPosition;
+use crate::schema::position::ObjectOrInterfaceFieldDefinitionPosition;
 use crate::schema::position::ObjectTypeDefinitionPosition;
 use crate::schema::position::ScalarTypeDefinitionPosition;
 use crate::schema::FederationSchema;
 
 pub(crate) const COST_DIRECTIVE_NAME_IN_SPEC: Name = name!("cost");
 pub(crate) const COST_DIRECTIVE_NAME_DEFAULT: Name = name!("federation__cost");
+pub(crate) const COST_WEIGHT_ARGUMENT_NAME: Name = name!("weight");
 
 pub(crate) const LIST_SIZE_DIRECTIVE_NAME_IN_SPEC: Name = name!("listSize");
 pub(crate) const LIST_SIZE_DIRECTIVE_NAME_DEFAULT: Name = name!("federation__listSize");
+pub(crate) const LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME: Name = name!("assumedSize");
+pub(crate) const LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME: Name = name!("slicingArguments");
+pub(crate) const LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME: Name = name!("sizedFields");
+pub(crate) const LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME: Name =
+    name!("requireOneSlicingArgument");
 
 #[derive(Clone)]
 pub(crate) struct CostSpecDefinition {
@@ -40,11 +55,13 @@ macro_rules! propagate_demand_control_directives {
             subgraph_schema: &FederationSchema,
             source: &$directives_ty,
             dest: &mut $directives_ty,
+            coordinate: impl Display,
             original_directive_names: &IndexMap<Name, Name>,
         ) -> Result<(), FederationError> {
             let cost_directive_name = original_directive_names.get(&COST_DIRECTIVE_NAME_IN_SPEC);
             let cost_directive = cost_directive_name.and_then(|name| source.get(name.as_str()));
             if let Some(cost_directive) = cost_directive {
+                Self::validate_cost_weight(cost_directive, coordinate)?;
                 dest.push($wrap_ty(self.cost_directive(
                     subgraph_schema,
                     cost_directive.arguments.clone(),
@@ -80,6 +97,7 @@ macro_rules! propagate_demand_control_directives_to_position {
             let cost_directive =
                 cost_directive_name.and_then(|name| source.directives.get(name.as_str()));
             if let Some(cost_directive) = cost_directive {
+                Self::validate_cost_weight(cost_directive, dest)?;
                 dest.insert_directive(
                     subgraph_schema,
                     Component::from(
@@ -142,6 +160,130 @@ impl CostSpecDefinition {
         Ok(Directive { name, arguments })
     }
 
+    /// Reads the weight of a `@cost` directive application. Weights must not be negative.
+    pub(crate) fn cost_weight(field_directive: &Directive) -> Result<i32, FederationError> {
+        let weight = directive_required_int_argument(field_directive, &COST_WEIGHT_ARGUMENT_NAME)?;
+        if weight < 0 {
+            return Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Argument \"{}\" of directive \"@{}\" must not be negative, got {}.",
+                    COST_WEIGHT_ARGUMENT_NAME, field_directive.name, weight
+                ),
+            }
+            .into());
+        }
+        Ok(weight)
+    }
+
+    /// Checks the weight of a `@cost` directive application like [`Self::cost_weight`], including
+    /// the coordinate of the element that the directive is applied to in the error.
+    pub(crate) fn validate_cost_weight(
+        directive: &Directive,
+        coordinate: impl Display,
+    ) -> Result<(), FederationError> {
+        match Self::cost_weight(directive) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid \"@{}\" on \"{}\": {}",
+                    directive.name, coordinate, error
+                ),
+            }
+            .into()),
+        }
+    }
+
+    /// Checks that the `@listSize` application on an object or interface field, if there is one,
+    /// has a non-negative `assumedSize`, that its `slicingArguments` are all arguments of the field
+    /// (and not empty when `requireOneSlicingArgument` is true), and that its `sizedFields` are all
+    /// fields of the type that the field returns.
+    pub(crate) fn validate_list_size(
+        schema: &FederationSchema,
+        field_position: &ObjectOrInterfaceFieldDefinitionPosition,
+        original_directive_names: &IndexMap<Name, Name>,
+    ) -> Result<(), FederationError> {
+        let Some(list_size_directive_name) =
+            original_directive_names.get(&LIST_SIZE_DIRECTIVE_NAME_IN_SPEC)
+        else {
+            return Ok(());
+        };
+        let field = field_position.get(schema.schema())?;
+        let Some(list_size_directive) = field.directives.get(list_size_directive_name.as_str())
+        else {
+            return Ok(());
+        };
+        let list_size = ListSizeDirective::from_directive(list_size_directive)?;
+        if let Some(assumed_size) = list_size.assumed_size.filter(|size| *size < 0) {
+            return Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Argument \"{}\" of \"@{}\" on \"{}\" must be non-negative, got {}",
+                    LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME,
+                    list_size_directive_name,
+                    field_position,
+                    assumed_size
+                ),
+            }
+            .into());
+        }
+        if list_size.require_one_slicing_argument
+            && list_size
+                .slicing_arguments
+                .as_ref()
+                .is_some_and(|arguments| arguments.is_empty())
+        {
+            return Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "\"@{}\" on \"{}\" requires one slicing argument, but \"{}\" is empty",
+                    list_size_directive_name,
+                    field_position,
+                    LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME
+                ),
+            }
+            .into());
+        }
+        for slicing_argument in list_size.slicing_arguments.iter().flatten() {
+            if field.argument_by_name(slicing_argument).is_none() {
+                return Err(SingleFederationError::InvalidFederationSupergraph {
+                    message: format!(
+                        "Slicing argument \"{}\" of \"@{}\" on \"{}\" is not an argument of the field",
+                        slicing_argument, list_size_directive_name, field_position
+                    ),
+                }
+                .into());
+            }
+        }
+        let Some(sized_fields) = list_size.sized_fields else {
+            return Ok(());
+        };
+
+        let output_type_name = field.ty.inner_named_type();
+        let output_type_fields = match schema.schema().types.get(output_type_name) {
+            Some(ExtendedType::Object(object)) => &object.fields,
+            Some(ExtendedType::Interface(interface)) => &interface.fields,
+            _ => {
+                return Err(SingleFederationError::InvalidFederationSupergraph {
+                    message: format!(
+                        "\"@{}\" on \"{}\" has sized fields, but its type \"{}\" is not an object or interface type",
+                        list_size_directive_name, field_position, output_type_name
+                    ),
+                }
+                .into())
+            }
+        };
+        for sized_field in sized_fields {
+            if !output_type_fields.contains_key(sized_field.as_str()) {
+                return Err(SingleFederationError::InvalidFederationSupergraph {
+                    message: format!(
+                        "Sized field \"{}\" of \"@{}\" on \"{}\" is not a field of \"{}\"",
+                        sized_field, list_size_directive_name, field_position, output_type_name
+                    ),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     propagate_demand_control_directives!(
         propagate_demand_control_directives,
         apollo_compiler::ast::DirectiveList,
@@ -165,6 +307,44 @@ impl CostSpecDefinition {
     );
 }
 
+/// The arguments of a `@listSize` directive application.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ListSizeDirective {
+    /// The size of the list when it can't be told from the slicing arguments
+    pub(crate) assumed_size: Option<i32>,
+    /// The arguments of the field that limit the size of the list
+    pub(crate) slicing_arguments: Option<Vec<String>>,
+    /// The fields of the returned type that hold the list, when it isn't the type itself
+    pub(crate) sized_fields: Option<Vec<String>>,
+    /// Whether a query must provide exactly one of the slicing arguments
+    pub(crate) require_one_slicing_argument: bool,
+}
+
+impl ListSizeDirective {
+    pub(crate) fn from_directive(directive: &Directive) -> Result<Self, FederationError> {
+        Ok(Self {
+            assumed_size: directive_optional_int_argument(
+                directive,
+                &LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME,
+            )?,
+            slicing_arguments: directive_optional_string_list_argument(
+                directive,
+                &LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME,
+            )?,
+            sized_fields: directive_optional_string_list_argument(
+                directive,
+                &LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME,
+            )?,
+            // The spec defaults this to true
+            require_one_slicing_argument: directive_optional_boolean_argument(
+                directive,
+                &LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME,
+            )?
+            .unwrap_or(true),
+        })
+    }
+}
+
 impl SpecDefinition for CostSpecDefinition {
     fn url(&self) -> &Url {
         &self.url
@@ -185,3 +365,146 @@ lazy_static! {
         definitions
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use apollo_compiler::ast::Value;
+
+    use super::*;
+
+    fn cost(weight: Value) -> Directive {
+        Directive {
+            name: COST_DIRECTIVE_NAME_IN_SPEC,
+            arguments: vec![Node::new(Argument {
+                name: COST_WEIGHT_ARGUMENT_NAME,
+                value: Node::new(weight),
+            })],
+        }
+    }
+
+    #[test]
+    fn reads_cost_weight() {
+        assert_eq!(
+            CostSpecDefinition::cost_weight(&cost(Value::from(15))).unwrap(),
+            15
+        );
+        assert_eq!(
+            CostSpecDefinition::cost_weight(&cost(Value::from(0))).unwrap(),
+            0
+        );
+    }
+
+    fn list_size(arguments: Vec<(Name, Value)>) -> Directive {
+        Directive {
+            name: LIST_SIZE_DIRECTIVE_NAME_IN_SPEC,
+            arguments: arguments
+                .into_iter()
+                .map(|(name, value)| {
+                    Node::new(Argument {
+                        name,
+                        value: Node::new(value),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn strings(values: &[&str]) -> Value {
+        Value::List(
+            values
+                .iter()
+                .map(|value| Node::new(Value::String((*value).into())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn reads_list_size_directive() {
+        assert_eq!(
+            ListSizeDirective::from_directive(&list_size(vec![
+                (LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME, Value::from(10)),
+                (
+                    LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME,
+                    strings(&["first", "last"])
+                ),
+                (LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME, strings(&["edges"])),
+                (
+                    LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME,
+                    Value::Boolean(false)
+                ),
+            ]))
+            .unwrap(),
+            ListSizeDirective {
+                assumed_size: Some(10),
+                slicing_arguments: Some(vec!["first".to_string(), "last".to_string()]),
+                sized_fields: Some(vec!["edges".to_string()]),
+                require_one_slicing_argument: false,
+            }
+        );
+        assert_eq!(
+            ListSizeDirective::from_directive(&list_size(vec![])).unwrap(),
+            ListSizeDirective {
+                assumed_size: None,
+                slicing_arguments: None,
+                sized_fields: None,
+                require_one_slicing_argument: true,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_list_size_directive() {
+        assert!(ListSizeDirective::from_directive(&list_size(vec![(
+            LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME,
+            Value::List(vec![Node::new(Value::from(1))])
+        )]))
+        .is_err());
+        assert!(ListSizeDirective::from_directive(&list_size(vec![(
+            LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME,
+            Value::Boolean(true)
+        )]))
+        .is_err());
+        assert!(ListSizeDirective::from_directive(&list_size(vec![(
+            LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME,
+            strings(&["10"])
+        )]))
+        .is_err());
+        assert!(ListSizeDirective::from_directive(&list_size(vec![(
+            LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME,
+            Value::from(1)
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn validates_cost_weight() {
+        let coordinate = "Query.field";
+        assert!(
+            CostSpecDefinition::validate_cost_weight(&cost(Value::from(0)), coordinate).is_ok()
+        );
+        assert_eq!(
+            CostSpecDefinition::validate_cost_weight(&cost(Value::from(-5)), coordinate)
+                .unwrap_err()
+                .to_string(),
+            r#"Invalid "@cost" on "Query.field": Argument "weight" of directive "@cost" must not be negative, got -5."#
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_cost_weight() {
+        assert!(matches!(
+            CostSpecDefinition::cost_weight(&cost(Value::from(-1))),
+            Err(FederationError::SingleFederationError {
+                inner: SingleFederationError::InvalidFederationSupergraph { .. },
+                ..
+            })
+        ));
+        assert!(CostSpecDefinition::cost_weight(&cost(Value::Boolean(true))).is_err());
+        assert!(CostSpecDefinition::cost_weight(&cost(Value::Null)).is_err());
+        assert!(CostSpecDefinition::cost_weight(&Directive {
+            name: COST_DIRECTIVE_NAME_IN_SPEC,
+            arguments: vec![],
+        })
+        .is_err());
+    }
+}
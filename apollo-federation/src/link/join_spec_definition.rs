@@ -1,3 +1,6 @@
+use std::ops::Deref;
+
+use apollo_compiler::ast::Value;
 use apollo_compiler::name;
 use apollo_compiler::schema::Directive;
 use apollo_compiler::schema::DirectiveDefinition;
@@ -45,6 +48,9 @@ pub(crate) const JOIN_OVERRIDE_LABEL_ARGUMENT_NAME: Name = name!("overrideLabel"
 pub(crate) const JOIN_USEROVERRIDDEN_ARGUMENT_NAME: Name = name!("usedOverridden");
 pub(crate) const JOIN_INTERFACE_ARGUMENT_NAME: Name = name!("interface");
 pub(crate) const JOIN_MEMBER_ARGUMENT_NAME: Name = name!("member");
+pub(crate) const JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME: Name = name!("contextArguments");
+pub(crate) const JOIN_CONTEXT_ARGUMENT_NAME: Name = name!("context");
+pub(crate) const JOIN_SELECTION_ARGUMENT_NAME: Name = name!("selection");
 
 pub(crate) struct GraphDirectiveArguments<'doc> {
     pub(crate) name: &'doc str,
@@ -68,6 +74,16 @@ pub(crate) struct FieldDirectiveArguments<'doc> {
     pub(crate) override_: Option<&'doc str>,
     pub(crate) override_label: Option<&'doc str>,
     pub(crate) user_overridden: Option<bool>,
+    pub(crate) context_arguments: Option<Vec<ContextArgument<'doc>>>,
+}
+
+/// An element of the `contextArguments` of `@join__field`, describing an argument of the field
+/// whose value is provided by `@fromContext` in the subgraph.
+pub(crate) struct ContextArgument<'doc> {
+    pub(crate) name: &'doc str,
+    pub(crate) type_: &'doc str,
+    pub(crate) context: &'doc str,
+    pub(crate) selection: &'doc str,
 }
 
 pub(crate) struct ImplementsDirectiveArguments<'doc> {
@@ -228,9 +244,53 @@ impl JoinSpecDefinition {
                 application,
                 &JOIN_USEROVERRIDDEN_ARGUMENT_NAME,
             )?,
+            context_arguments: Self::context_arguments(application)?,
         })
     }
 
+    fn context_arguments<'doc>(
+        application: &'doc Node<Directive>,
+    ) -> Result<Option<Vec<ContextArgument<'doc>>>, FederationError> {
+        let Some(value) =
+            application.specified_argument_by_name(&JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME)
+        else {
+            return Ok(None);
+        };
+        let invalid = || SingleFederationError::Internal {
+            message: format!(
+                "Argument \"{}\" of directive \"@{}\" must be a list of context arguments.",
+                JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME, application.name
+            ),
+        };
+        let items = match value.deref() {
+            Value::Null => return Ok(None),
+            Value::List(items) => items.as_slice(),
+            // Input coercion allows a single item in place of a list
+            Value::Object(_) => std::slice::from_ref(value),
+            _ => return Err(invalid().into()),
+        };
+        let context_arguments = items
+            .iter()
+            .map(|item| -> Result<_, FederationError> {
+                let fields = item.as_object().ok_or_else(invalid)?;
+                let field = |name: &Name| {
+                    fields
+                        .iter()
+                        .find(|(field_name, _)| field_name == name)
+                        .and_then(|(_, value)| value.as_str())
+                        .ok_or_else(invalid)
+                };
+                Ok(ContextArgument {
+                    name: field(&JOIN_NAME_ARGUMENT_NAME)?,
+                    type_: field(&JOIN_TYPE_ARGUMENT_NAME)?,
+                    context: field(&JOIN_CONTEXT_ARGUMENT_NAME)?,
+                    selection: field(&JOIN_SELECTION_ARGUMENT_NAME)?,
+                })
+            })
+            .collect::<Result<Vec<_>, FederationError>>()?;
+        Ok(Some(context_arguments))
+    }
+
     pub(crate) fn implements_directive_definition<'schema>(
         &self,
         schema: &'schema FederationSchema,
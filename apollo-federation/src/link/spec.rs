@@ -89,12 +89,26 @@ impl Identity {
         }
     }
 
+    pub fn tag_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("tag"),
+        }
+    }
+
     pub fn cost_identity() -> Identity {
         Identity {
             domain: APOLLO_SPEC_DOMAIN.to_string(),
             name: name!("cost"),
         }
     }
+
+    pub fn context_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("context"),
+        }
+    }
 }
 
 /// The version of a `@link` specification, in the form of a major and minor version numbers.
@@ -13,6 +13,7 @@ use super::InlineFragmentSelection;
 use super::InlineFragmentSelectionValue;
 use super::NamedFragments;
 use super::Selection;
+use super::SelectionMap;
 use super::SelectionSet;
 use super::SelectionValue;
 use crate::error::FederationError;
@@ -142,43 +143,25 @@ impl<'a> FragmentSpreadSelectionValue<'a> {
     }
 }
 
-impl SelectionSet {
-    /// NOTE: This is a private API and should be used with care, use `add_selection_set` instead.
+impl SelectionMap {
+    /// Inserts a selection into the map. Should a selection with the same key already exist in
+    /// the map, the given selection's sub-selections are merged into the existing selection,
+    /// which keeps its insertion index. Unlike [`SelectionMap::insert`], this never discards the
+    /// existing selection.
     ///
-    /// Merges the given normalized selection sets into this one.
+    /// # Preconditions
+    /// The provided selection must have the same schema and parent type as the selections
+    /// already in the map.
     ///
     /// # Errors
-    /// Returns an error if the parent type or schema of any selection does not match `self`'s.
-    ///
-    /// Returns an error if any selection contains invalid GraphQL that prevents the merge.
-    fn merge_into<'op>(
-        &mut self,
-        others: impl Iterator<Item = &'op SelectionSet>,
-    ) -> Result<(), FederationError> {
-        let mut selections_to_merge = vec![];
-        for other in others {
-            if other.schema != self.schema {
-                return Err(FederationError::internal(
-                    "Cannot merge selection sets from different schemas",
-                ));
-            }
-            if other.type_position != self.type_position {
-                return Err(FederationError::internal(
-                    format!(
-                        "Cannot merge selection set for type \"{}\" into a selection set for type \"{}\"",
-                        other.type_position,
-                        self.type_position,
-                    ),
-                ));
-            }
-            selections_to_merge.extend(other.selections.values());
-        }
-        self.merge_selections_into(selections_to_merge.into_iter())
+    /// Returns an error if either selection contains invalid GraphQL that prevents the merge.
+    pub(crate) fn insert_merged(&mut self, selection: &Selection) -> Result<(), FederationError> {
+        self.merge_selections_into(std::iter::once(selection))
     }
 
-    /// NOTE: This is a private API and should be used with care, use `add_selection` instead.
-    ///
-    /// A helper function for merging the given selections into this one.
+    /// Merges the given selections into the map. Selections whose key is not in the map yet are
+    /// appended, and the others are merged into the existing selection with the same key, which
+    /// keeps its insertion index.
     ///
     /// # Errors
     /// Returns an error if the parent type or schema of any selection does not match `self`'s.
@@ -191,10 +174,9 @@ impl SelectionSet {
         let mut fields = IndexMap::default();
         let mut fragment_spreads = IndexMap::default();
         let mut inline_fragments = IndexMap::default();
-        let target = Arc::make_mut(&mut self.selections);
         for other_selection in others {
             let other_key = other_selection.key();
-            match target.entry(other_key.clone()) {
+            match self.entry(other_key.clone()) {
                 selection_map::Entry::Occupied(existing) => match existing.get() {
                     Selection::Field(self_field_selection) => {
                         let Selection::Field(other_field_selection) = other_selection else {
@@ -254,7 +236,7 @@ impl SelectionSet {
             }
         }
 
-        for (key, self_selection) in target.iter_mut() {
+        for (key, self_selection) in self.iter_mut() {
             match self_selection {
                 SelectionValue::Field(mut self_field_selection) => {
                     if let Some(other_field_selections) = fields.shift_remove(key) {
@@ -290,6 +272,56 @@ impl SelectionSet {
 
         Ok(())
     }
+}
+
+impl SelectionSet {
+    /// NOTE: This is a private API and should be used with care, use `add_selection_set` instead.
+    ///
+    /// Merges the given normalized selection sets into this one.
+    ///
+    /// # Errors
+    /// Returns an error if the parent type or schema of any selection does not match `self`'s.
+    ///
+    /// Returns an error if any selection contains invalid GraphQL that prevents the merge.
+    fn merge_into<'op>(
+        &mut self,
+        others: impl Iterator<Item = &'op SelectionSet>,
+    ) -> Result<(), FederationError> {
+        let mut selections_to_merge = vec![];
+        for other in others {
+            if other.schema != self.schema {
+                return Err(FederationError::internal(
+                    "Cannot merge selection sets from different schemas",
+                ));
+            }
+            if other.type_position != self.type_position {
+                return Err(FederationError::internal(
+                    format!(
+                        "Cannot merge selection set for type \"{}\" into a selection set for type \"{}\"",
+                        other.type_position,
+                        self.type_position,
+                    ),
+                ));
+            }
+            selections_to_merge.extend(other.selections.values());
+        }
+        self.merge_selections_into(selections_to_merge.into_iter())
+    }
+
+    /// NOTE: This is a private API and should be used with care, use `add_selection` instead.
+    ///
+    /// A helper function for merging the given selections into this one.
+    ///
+    /// # Errors
+    /// Returns an error if the parent type or schema of any selection does not match `self`'s.
+    ///
+    /// Returns an error if any selection contains invalid GraphQL that prevents the merge.
+    pub(super) fn merge_selections_into<'op>(
+        &mut self,
+        others: impl Iterator<Item = &'op Selection>,
+    ) -> Result<(), FederationError> {
+        Arc::make_mut(&mut self.selections).merge_selections_into(others)
+    }
 
     /// Inserts a `Selection` into the inner map. Should a selection with the same key already
     /// exist in the map, the existing selection and the given selection are merged, replacing the
@@ -311,7 +343,7 @@ impl SelectionSet {
             selection.schema(),
             "In order to add selection it needs to point to the same schema"
         );
-        self.merge_selections_into(std::iter::once(selection))
+        Arc::make_mut(&mut self.selections).insert_merged(selection)
     }
 
     /// Inserts a `SelectionSet` into the inner map. Should any sub selection with the same key already
@@ -63,6 +63,10 @@ mod tests;
 pub(crate) use contains::*;
 pub(crate) use directive_list::DirectiveList;
 pub(crate) use merging::*;
+pub use optimize::FragmentNaming;
+pub use optimize::OptimizeMode;
+pub use optimize::OptimizeOptions;
+pub use optimize::OptimizeStats;
 pub(crate) use rebase::*;
 
 pub(crate) const TYPENAME_FIELD: Name = name!("__typename");
@@ -237,6 +241,16 @@ impl Operation {
             named_fragments,
         })
     }
+
+    /// The selection set of the operation.
+    pub fn selection_set(&self) -> &SelectionSet {
+        &self.selection_set
+    }
+
+    /// The named fragments used by the operation.
+    pub fn named_fragments(&self) -> &NamedFragments {
+        &self.named_fragments
+    }
 }
 
 /// An analogue of the apollo-compiler type `SelectionSet` with these changes:
@@ -244,7 +258,7 @@ impl Operation {
 ///   `NamedType`.
 /// - Stores selections in a map so they can be normalized efficiently.
 #[derive(Debug, Clone, Serialize)]
-pub(crate) struct SelectionSet {
+pub struct SelectionSet {
     #[serde(skip)]
     pub(crate) schema: ValidFederationSchema,
     pub(crate) type_position: CompositeTypeDefinitionPosition,
@@ -289,6 +303,11 @@ mod selection_map {
     /// `IndexSet` since key computation is expensive (it involves sorting). This type is in its own
     /// module to prevent code from accidentally mutating the underlying map outside the mutation
     /// API.
+    ///
+    /// The underlying `IndexMap` stores the hash of each key next to its entry, so removing or
+    /// retaining selections re-indexes entries from those stored hashes without re-deriving or
+    /// re-hashing any key. Since `SelectionValue` does not allow mutating key-related data, the
+    /// stored hashes stay valid for as long as the selection is in the map.
     #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
     pub(crate) struct SelectionMap(IndexMap<SelectionKey, Selection>);
 
@@ -310,11 +329,39 @@ mod selection_map {
             self.0.clear();
         }
 
+        /// Inserts a selection into the map, replacing (and returning) any selection with the same
+        /// key. Use [`SelectionMap::insert_merged`] to merge with an existing selection instead.
         pub(crate) fn insert(&mut self, value: Selection) -> Option<Selection> {
             self.0.insert(value.key(), value)
         }
 
-        /// Remove a selection from the map. Returns the selection and its numeric index.
+        /// Inserts a selection into the map, unless a selection with the same key already exists.
+        /// Unlike [`SelectionMap::insert`], this never replaces an existing selection.
+        ///
+        /// # Errors
+        /// Returns an error naming the key if the map already contains a selection with that key.
+        pub(crate) fn try_insert(
+            &mut self,
+            value: Selection,
+        ) -> Result<SelectionValue, FederationError> {
+            match self.entry(value.key()) {
+                Entry::Occupied(entry) => Err(Internal {
+                    message: format!(
+                        "Cannot insert selection {value}: selection {} already exists with key {:?}",
+                        entry.get(),
+                        entry.0.key(),
+                    ),
+                }
+                .into()),
+                Entry::Vacant(entry) => entry.insert(value),
+            }
+        }
+
+        /// Remove a selection from the map, preserving the order of the remaining selections.
+        /// Returns the selection and its numeric index.
+        ///
+        /// This is O(n), as every selection after the removed one is shifted down by one. The
+        /// order of selections shows in query plans, so every caller needs it preserved.
         pub(crate) fn remove(&mut self, key: &SelectionKey) -> Option<(usize, Selection)> {
             // We specifically use shift_remove() instead of swap_remove() to maintain order.
             self.0
@@ -353,12 +400,50 @@ mod selection_map {
                 .extend(other.iter().map(|(k, v)| (k.clone(), v.clone())))
         }
 
+        /// Returns a new map with the selections of `self` whose key is not in `other`, in the
+        /// order of `self`.
+        ///
+        /// This only compares keys: selections are kept or dropped as a whole, and their
+        /// sub-selections are never compared. Use [`SelectionSet::minus`] for a difference that
+        /// recurses into sub-selections.
+        pub(crate) fn difference(&self, other: &SelectionMap) -> SelectionMap {
+            SelectionMap(
+                self.0
+                    .iter()
+                    .filter(|(key, _)| !other.contains_key(*key))
+                    .map(|(key, selection)| (key.clone(), selection.clone()))
+                    .collect(),
+            )
+        }
+
+        /// Returns a new map with the selections of `self` whose key is also in `other`, in the
+        /// order of `self`.
+        ///
+        /// This only compares keys: the selections of `self` are returned as they are, even when
+        /// the selection with the same key in `other` has different sub-selections. Use
+        /// [`SelectionSet::intersection`] for an intersection that recurses into sub-selections.
+        #[cfg(test)]
+        pub(crate) fn intersection(&self, other: &SelectionMap) -> SelectionMap {
+            SelectionMap(
+                self.0
+                    .iter()
+                    .filter(|(key, _)| other.contains_key(*key))
+                    .map(|(key, selection)| (key.clone(), selection.clone()))
+                    .collect(),
+            )
+        }
+
         /// Returns the selection set resulting from "recursively" filtering any selection
         /// that does not match the provided predicate.
         /// This method calls `predicate` on every selection of the selection set,
         /// not just top-level ones, and apply a "depth-first" strategy:
         /// when the predicate is called on a given selection it is guaranteed that
         /// filtering has happened on all the selections of its sub-selection.
+        ///
+        /// Fragment spreads are handled like inline fragments: the predicate is called on the
+        /// selections of the spread's `selection_set`, and a kept spread keeps referencing the same
+        /// named fragment. Note that the filtered `selection_set` may then no longer match the
+        /// fragment definition.
         pub(crate) fn filter_recursive_depth_first(
             &self,
             predicate: &mut dyn FnMut(&Selection) -> Result<bool, FederationError>,
@@ -393,9 +478,18 @@ mod selection_map {
                             )),
                         )),
                     },
-                    Selection::FragmentSpread(_) => {
-                        return Err(FederationError::internal("unexpected fragment spread"))
-                    }
+                    Selection::FragmentSpread(fragment) => match fragment
+                        .selection_set
+                        .filter_recursive_depth_first(predicate)?
+                    {
+                        Cow::Borrowed(_) => Cow::Borrowed(selection),
+                        Cow::Owned(selection_set) => Cow::Owned(Selection::FragmentSpread(
+                            Arc::new(FragmentSpreadSelection {
+                                spread: fragment.spread.clone(),
+                                selection_set,
+                            }),
+                        )),
+                    },
                 })
             }
             let mut iter = self.0.iter();
@@ -599,21 +693,23 @@ mod selection_map {
         }
     }
 
+    /// Iterating over a selection map yields its selections in order. The keys can be derived
+    /// from the selections, and are available through `iter()` when needed.
     impl IntoIterator for SelectionMap {
-        type Item = <IndexMap<SelectionKey, Selection> as IntoIterator>::Item;
-        type IntoIter = <IndexMap<SelectionKey, Selection> as IntoIterator>::IntoIter;
+        type Item = Selection;
+        type IntoIter = indexmap::map::IntoValues<SelectionKey, Selection>;
 
         fn into_iter(self) -> Self::IntoIter {
-            <IndexMap<SelectionKey, Selection> as IntoIterator>::into_iter(self.0)
+            self.0.into_values()
         }
     }
 
     impl<'a> IntoIterator for &'a SelectionMap {
-        type Item = <&'a IndexMap<SelectionKey, Selection> as IntoIterator>::Item;
-        type IntoIter = <&'a IndexMap<SelectionKey, Selection> as IntoIterator>::IntoIter;
+        type Item = &'a Selection;
+        type IntoIter = indexmap::map::Values<'a, SelectionKey, Selection>;
 
         fn into_iter(self) -> Self::IntoIter {
-            self.0.iter()
+            self.0.values()
         }
     }
 }
@@ -2130,7 +2226,14 @@ impl SelectionSet {
         Ok(())
     }
 
-    pub(crate) fn without_empty_branches(&self) -> Result<Option<Cow<'_, Self>>, FederationError> {
+    /// Removes the empty branches of the selection set: fields and inline fragments whose
+    /// sub-selections are empty. The removal cascades, so a branch that only contained empty
+    /// branches is removed too.
+    ///
+    /// Returns `None` if every selection was removed.
+    ///
+    /// The selection set must be fragment-spread-free, otherwise an error is returned.
+    pub fn without_empty_branches(&self) -> Result<Option<SelectionSet>, FederationError> {
         let filtered = self.filter_recursive_depth_first(&mut |sel| match sel {
             Selection::Field(field) => Ok(if let Some(set) = &field.selection_set {
                 !set.is_empty()
@@ -2145,7 +2248,7 @@ impl SelectionSet {
         Ok(if filtered.selections.is_empty() {
             None
         } else {
-            Some(filtered)
+            Some(filtered.into_owned())
         })
     }
 
@@ -2376,11 +2479,12 @@ impl SelectionSet {
                     Field::new_introspection_typename(&self.schema, &parent.into(), None),
                     None,
                 );
-                selection_map.insert(typename_selection);
+                selection_map.try_insert(typename_selection)?;
             }
         }
         for selection in self.selections.values() {
-            selection_map.insert(if let Some(selection_set) = selection.selection_set() {
+            // Updating the sub-selections keeps the selection's key, so keys stay unique.
+            selection_map.try_insert(if let Some(selection_set) = selection.selection_set() {
                 let abstract_type = match selection {
                     Selection::Field(field_selection) => field_selection
                         .selection_set
@@ -2407,7 +2511,7 @@ impl SelectionSet {
                 }
             } else {
                 selection.clone()
-            });
+            })?;
         }
 
         Ok(SelectionSet {
@@ -2792,8 +2896,8 @@ impl SelectionSet {
 }
 
 impl IntoIterator for SelectionSet {
-    type Item = <IndexMap<SelectionKey, Selection> as IntoIterator>::Item;
-    type IntoIter = <IndexMap<SelectionKey, Selection> as IntoIterator>::IntoIter;
+    type Item = <SelectionMap as IntoIterator>::Item;
+    type IntoIter = <SelectionMap as IntoIterator>::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         Arc::unwrap_or_clone(self.selections).into_iter()
@@ -2801,8 +2905,8 @@ impl IntoIterator for SelectionSet {
 }
 
 impl<'a> IntoIterator for &'a SelectionSet {
-    type Item = <&'a IndexMap<SelectionKey, Selection> as IntoIterator>::Item;
-    type IntoIter = <&'a IndexMap<SelectionKey, Selection> as IntoIterator>::IntoIter;
+    type Item = <&'a SelectionMap as IntoIterator>::Item;
+    type IntoIter = <&'a SelectionMap as IntoIterator>::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         self.selections.as_ref().into_iter()
@@ -3283,12 +3387,14 @@ impl InlineFragmentSelection {
 /// unlike in JS where we can have multiple references to a mutable map,
 /// here modifying a cloned map will leave the original unchanged.
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
-pub(crate) struct NamedFragments {
+pub struct NamedFragments {
     fragments: Arc<IndexMap<Name, Node<Fragment>>>,
 }
 
 impl NamedFragments {
-    pub(crate) fn new(
+    /// Builds the named fragments from the fragment definitions of an executable document,
+    /// normalizing them against `schema`.
+    pub fn new(
         fragments: &IndexMap<Name, Node<executable::Fragment>>,
         schema: &ValidFederationSchema,
     ) -> NamedFragments {
@@ -3300,11 +3406,11 @@ impl NamedFragments {
         NamedFragments::initialize_in_dependency_order(fragments, schema)
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
+    pub fn is_empty(&self) -> bool {
         self.fragments.len() == 0
     }
 
-    pub(crate) fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         self.fragments.len()
     }
 
@@ -3333,7 +3439,7 @@ impl NamedFragments {
         self.fragments.get(name)
     }
 
-    pub(crate) fn contains(&self, name: &str) -> bool {
+    pub fn contains(&self, name: &str) -> bool {
         self.fragments.contains_key(name)
     }
 
@@ -3479,10 +3585,7 @@ impl DeferNormalizer {
             assigned_labels: IndexSet::default(),
             conditions: IndexMap::default(),
         };
-        let mut stack = selection_set
-            .into_iter()
-            .map(|(_, sel)| sel)
-            .collect::<Vec<_>>();
+        let mut stack = selection_set.into_iter().collect::<Vec<_>>();
         while let Some(selection) = stack.pop() {
             if let Selection::InlineFragment(inline) = selection {
                 if let Some(args) = inline.inline_fragment.data().defer_directive_arguments()? {
@@ -3492,13 +3595,7 @@ impl DeferNormalizer {
                     }
                 }
             }
-            stack.extend(
-                selection
-                    .selection_set()
-                    .into_iter()
-                    .flatten()
-                    .map(|(_, sel)| sel),
-            );
+            stack.extend(selection.selection_set().into_iter().flatten());
         }
         Ok(digest)
     }
@@ -3806,7 +3903,7 @@ impl SelectionSet {
         } = self;
         Arc::unwrap_or_clone(selections)
             .into_iter()
-            .map(|(_, sel)| sel.normalize_defer(normalizer))
+            .map(|sel| sel.normalize_defer(normalizer))
             .try_collect()
             .map(|selections| Self {
                 schema,
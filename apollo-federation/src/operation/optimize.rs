@@ -35,6 +35,7 @@
 //! ## `reuse_fragments` methods (putting everything together)
 //! Recursive optimization of selection and selection sets.
 
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use apollo_compiler::collections::IndexMap;
@@ -43,6 +44,8 @@ use apollo_compiler::executable;
 use apollo_compiler::executable::VariableDefinition;
 use apollo_compiler::Name;
 use apollo_compiler::Node;
+use sha1::Digest;
+use sha1::Sha1;
 
 use super::Containment;
 use super::ContainmentOptions;
@@ -58,23 +61,31 @@ use super::Selection;
 use super::SelectionMapperReturn;
 use super::SelectionOrSet;
 use super::SelectionSet;
+use super::VariableCollector;
 use crate::error::FederationError;
 use crate::operation::FragmentSpread;
 use crate::operation::FragmentSpreadData;
 use crate::operation::SelectionValue;
 use crate::schema::position::CompositeTypeDefinitionPosition;
+use crate::schema::ValidFederationSchema;
 
 #[derive(Debug)]
 struct ReuseContext<'a> {
     fragments: &'a NamedFragments,
     operation_variables: Option<IndexSet<&'a Name>>,
+    /// The maximum nesting depth of selection sets to reuse fragments in. Reusing fragments
+    /// recurses through the selection set, so this guards against overflowing the stack.
+    max_depth: usize,
 }
 
 impl<'a> ReuseContext<'a> {
+    const DEFAULT_MAX_DEPTH: usize = 500;
+
     fn for_fragments(fragments: &'a NamedFragments) -> Self {
         Self {
             fragments,
             operation_variables: None,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
         }
     }
 
@@ -86,8 +97,13 @@ impl<'a> ReuseContext<'a> {
         Self {
             fragments,
             operation_variables: Some(operation_variables.iter().map(|var| &var.name).collect()),
+            max_depth: Self::DEFAULT_MAX_DEPTH,
         }
     }
+
+    fn with_max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
 }
 
 //=============================================================================
@@ -198,11 +214,14 @@ impl NamedFragments {
             ss.add_typename_field_for_abstract_types(/*parent_type_if_abstract*/ None)
         })?;
         // PORT_NOTE: The JS version asserts if `updated` is empty or not. But, we really want to
-        // check the `updated` has the same set of fragments. To avoid performance hit, only the
-        // size is checked here.
-        if updated.len() != self.len() {
+        // check the `updated` has the same set of fragments.
+        if updated.len() != self.len()
+            || self
+                .iter()
+                .any(|fragment| !updated.contains(&fragment.name))
+        {
             return Err(FederationError::internal(
-                "Unexpected change in the number of fragments",
+                "Unexpected change in the set of fragments",
             ));
         }
         Ok(updated)
@@ -225,13 +244,10 @@ impl Selection {
         if let (Some(self_sub_selection), Some(other_sub_selection)) =
             (self.selection_set(), other.selection_set())
         {
-            let diff = self_sub_selection.minus(other_sub_selection)?;
+            let diff = self_sub_selection.minus_inner(other_sub_selection, Default::default())?;
             if !diff.is_empty() {
                 return self
-                    .with_updated_selections(
-                        self_sub_selection.type_position.clone(),
-                        diff.into_iter().map(|(_, v)| v),
-                    )
+                    .with_updated_selections(self_sub_selection.type_position.clone(), diff)
                     .map(Some);
             }
         }
@@ -246,15 +262,13 @@ impl Selection {
         if let (Some(self_sub_selection), Some(other_sub_selection)) =
             (self.selection_set(), other.selection_set())
         {
-            let common = self_sub_selection.intersection(other_sub_selection)?;
+            let common =
+                self_sub_selection.intersection_inner(other_sub_selection, Default::default())?;
             if common.is_empty() {
                 return Ok(None);
             } else {
                 return self
-                    .with_updated_selections(
-                        self_sub_selection.type_position.clone(),
-                        common.into_iter().map(|(_, v)| v),
-                    )
+                    .with_updated_selections(self_sub_selection.type_position.clone(), common)
                     .map(Some);
             }
         }
@@ -262,10 +276,45 @@ impl Selection {
     }
 }
 
+/// Options for `SelectionSet::minus_with_options` and `SelectionSet::intersection_with_options`.
+#[derive(Debug, Clone, Copy, Default)]
+struct SetOperationOptions {
+    /// Also compare against `other` re-normalized at the type of `self`, when the two selection
+    /// sets are on different types. Selections that were simplified differently due to being
+    /// normalized on different parent types are then matched.
+    renormalize_other: bool,
+}
+
 impl SelectionSet {
     /// Performs set-subtraction (self - other) and returns the result (the difference between self
     /// and other).
+    ///
+    /// # Preconditions
+    /// Neither selection set may contain named fragment spreads, since selections are compared by
+    /// key without expanding fragments.
     pub(crate) fn minus(&self, other: &SelectionSet) -> Result<SelectionSet, FederationError> {
+        self.minus_with_options(other, Default::default())
+    }
+
+    fn minus_with_options(
+        &self,
+        other: &SelectionSet,
+        options: SetOperationOptions,
+    ) -> Result<SelectionSet, FederationError> {
+        // Checked once here rather than at every level of the recursion, as it walks both
+        // selection sets entirely.
+        debug_assert!(
+            !self.contains_fragment_spread() && !other.contains_fragment_spread(),
+            "minus() must only be used on selection sets that do not contain named fragment spreads"
+        );
+        self.minus_inner(other, options)
+    }
+
+    fn minus_inner(
+        &self,
+        other: &SelectionSet,
+        options: SetOperationOptions,
+    ) -> Result<SelectionSet, FederationError> {
         let iter = self
             .selections
             .iter()
@@ -279,15 +328,52 @@ impl SelectionSet {
             .collect::<Result<Vec<_>, _>>()? // early break in case of Err
             .into_iter()
             .flatten();
-        Ok(SelectionSet::from_raw_selections(
+        let diff = SelectionSet::from_raw_selections(
             self.schema.clone(),
             self.type_position.clone(),
             iter,
-        ))
+        );
+        match self.renormalized(other, options)? {
+            // Only selections that are covered by `other` are removed, so subtracting the
+            // re-normalized `other` as well can only make the difference tighter.
+            Some(renormalized) if !diff.is_empty() => {
+                diff.minus_inner(&renormalized, Default::default())
+            }
+            _ => Ok(diff),
+        }
     }
 
     /// Computes the set-intersection of self and other
-    fn intersection(&self, other: &SelectionSet) -> Result<SelectionSet, FederationError> {
+    ///
+    /// # Preconditions
+    /// Neither selection set may contain named fragment spreads, since selections are compared by
+    /// key without expanding fragments.
+    pub(crate) fn intersection(
+        &self,
+        other: &SelectionSet,
+    ) -> Result<SelectionSet, FederationError> {
+        self.intersection_with_options(other, Default::default())
+    }
+
+    fn intersection_with_options(
+        &self,
+        other: &SelectionSet,
+        options: SetOperationOptions,
+    ) -> Result<SelectionSet, FederationError> {
+        // Checked once here rather than at every level of the recursion, as it walks both
+        // selection sets entirely.
+        debug_assert!(
+            !self.contains_fragment_spread() && !other.contains_fragment_spread(),
+            "intersection() must only be used on selection sets that do not contain named fragment spreads"
+        );
+        self.intersection_inner(other, options)
+    }
+
+    fn intersection_inner(
+        &self,
+        other: &SelectionSet,
+        options: SetOperationOptions,
+    ) -> Result<SelectionSet, FederationError> {
         if self.is_empty() {
             return Ok(self.clone());
         }
@@ -308,11 +394,45 @@ impl SelectionSet {
             .collect::<Result<Vec<_>, _>>()? // early break in case of Err
             .into_iter()
             .flatten();
-        Ok(SelectionSet::from_raw_selections(
+        let mut common = SelectionSet::from_raw_selections(
             self.schema.clone(),
             self.type_position.clone(),
             iter,
-        ))
+        );
+        if let Some(renormalized) = self.renormalized(other, options)? {
+            common.add_local_selection_set(
+                &self.intersection_inner(&renormalized, Default::default())?,
+            )?;
+        }
+        Ok(common)
+    }
+
+    /// Returns `other` normalized at the type of `self`, if requested by `options` and if the two
+    /// selection sets are on different types.
+    //
+    // `other` is wrapped in an inline fragment on its own type first, so that its selections
+    // remain valid at the type of `self`. The wrapping fragment is then flattened away if
+    // unnecessary.
+    fn renormalized(
+        &self,
+        other: &SelectionSet,
+        options: SetOperationOptions,
+    ) -> Result<Option<SelectionSet>, FederationError> {
+        if !options.renormalize_other || other.type_position == self.type_position {
+            return Ok(None);
+        }
+        let wrapped = InlineFragmentSelection::from_selection_set(
+            self.type_position.clone(),
+            other.clone(),
+            Default::default(),
+        );
+        SelectionSet::from_selection(self.type_position.clone(), wrapped.into())
+            .flatten_unnecessary_fragments(
+                &self.type_position,
+                /*named_fragments*/ &Default::default(),
+                &self.schema,
+            )
+            .map(Some)
     }
 }
 
@@ -353,11 +473,16 @@ impl Fragment {
         // PORT_NOTE: In JS, this check was later on the return statement (negated). But, this
         //            should be checked before `possible_runtime_types` check, since this is
         //            cheaper to execute.
-        // PORT_NOTE: This condition may be too restrictive (potentially a bug leading to
-        //            suboptimal compression). If ty is a union whose members all implements the
-        //            type condition (interface). Then, this function should've returned true.
-        //            Thus, `!ty.is_union_type()` might be needed.
-        if !self.type_condition_position.is_union_type() && !ty.is_object_type() {
+        // - If `ty` is a union whose members all implement the type condition (interface), the
+        //   fragment may still apply, as long as it does not select any interface field directly,
+        //   since those cannot be selected at the union type. In that case, the superset check
+        //   below decides.
+        // PORT_NOTE: In JS, a union `ty` was always rejected here, which led to suboptimal
+        //            compression.
+        if !self.type_condition_position.is_union_type()
+            && !ty.is_object_type()
+            && !(ty.is_union_type() && self.has_no_direct_field_selections())
+        {
             return Ok(false);
         }
 
@@ -369,6 +494,14 @@ impl Fragment {
         let ty_types = self.schema.possible_runtime_types(ty.clone())?;
         Ok(condition_types.is_superset(&ty_types))
     }
+
+    /// Whether the top-level selections of this fragment are only fragments or `__typename`.
+    fn has_no_direct_field_selections(&self) -> bool {
+        self.selection_set
+            .selections
+            .iter()
+            .all(|(key, selection)| !selection.is_field() || key.is_typename_field())
+    }
 }
 
 impl NamedFragments {
@@ -384,6 +517,52 @@ impl NamedFragments {
                 .transpose()
         })
     }
+
+    /// Returns the fragments with any selection that uses a variable not declared by the operation
+    /// removed. Fragments that end up without any selection are dropped.
+    //
+    // Fragments may introduce fields that use variables that are not declared in the operation
+    // (for instance, when a fetch only needs part of a user-provided fragment). Rather than not
+    // reusing those fragments at all, we only keep the part of them that the operation can use.
+    fn trim_undeclared_variables(
+        &self,
+        operation_variables: &IndexSet<&Name>,
+    ) -> Result<Cow<'_, NamedFragments>, FederationError> {
+        if self
+            .iter()
+            .all(|fragment| fragment.used_variables().is_subset(operation_variables))
+        {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let mut trimmed = self.map_to_expanded_selection_sets(|selection_set| {
+            let filtered = selection_set.filter_recursive_depth_first(&mut |selection| {
+                let mut collector = VariableCollector::new();
+                match selection {
+                    Selection::Field(field) => {
+                        // Sub-selections may have been emptied by the trimming.
+                        if field.selection_set.as_ref().is_some_and(|s| s.is_empty()) {
+                            return Ok(false);
+                        }
+                        collector.visit_field(&field.field);
+                    }
+                    Selection::InlineFragment(inline) => {
+                        if inline.selection_set.is_empty() {
+                            return Ok(false);
+                        }
+                        collector.visit_inline_fragment(&inline.inline_fragment);
+                    }
+                    Selection::FragmentSpread(_) => {
+                        return Err(FederationError::internal("unexpected fragment spread"))
+                    }
+                }
+                Ok(collector.into_inner().is_subset(operation_variables))
+            })?;
+            Ok(filtered.into_owned())
+        })?;
+        trimmed.retain(|_, fragment| !fragment.selection_set.is_empty());
+        Ok(Cow::Owned(trimmed))
+    }
 }
 
 //=============================================================================
@@ -451,8 +630,23 @@ impl FieldsConflictValidator {
     }
 
     fn has_same_response_shape(
+        self: &Arc<Self>,
+        other: &Arc<Self>,
+        cache: &mut FieldsConflictValidatorCache,
+    ) -> Result<bool, FederationError> {
+        let key = (Arc::as_ptr(self), Arc::as_ptr(other));
+        if let Some(result) = cache.has_same_response_shape.get(&key) {
+            return Ok(*result);
+        }
+        let result = self.compute_has_same_response_shape(other, cache)?;
+        cache.has_same_response_shape.insert(key, result);
+        Ok(result)
+    }
+
+    fn compute_has_same_response_shape(
         &self,
         other: &FieldsConflictValidator,
+        cache: &mut FieldsConflictValidatorCache,
     ) -> Result<bool, FederationError> {
         for (response_name, self_fields) in self.by_response_name.iter() {
             let Some(other_fields) = other.by_response_name.get(response_name) else {
@@ -467,7 +661,7 @@ impl FieldsConflictValidator {
 
                     if let Some(self_validator) = self_validator {
                         if let Some(other_validator) = other_validator {
-                            if !self_validator.has_same_response_shape(other_validator)? {
+                            if !self_validator.has_same_response_shape(other_validator, cache)? {
                                 return Ok(false);
                             }
                         }
@@ -478,7 +672,25 @@ impl FieldsConflictValidator {
         Ok(true)
     }
 
-    fn do_merge_with(&self, other: &FieldsConflictValidator) -> Result<bool, FederationError> {
+    fn do_merge_with(
+        self: &Arc<Self>,
+        other: &Arc<Self>,
+        cache: &mut FieldsConflictValidatorCache,
+    ) -> Result<bool, FederationError> {
+        let key = (Arc::as_ptr(self), Arc::as_ptr(other));
+        if let Some(result) = cache.do_merge_with.get(&key) {
+            return Ok(*result);
+        }
+        let result = self.compute_do_merge_with(other, cache)?;
+        cache.do_merge_with.insert(key, result);
+        Ok(result)
+    }
+
+    fn compute_do_merge_with(
+        &self,
+        other: &FieldsConflictValidator,
+        cache: &mut FieldsConflictValidatorCache,
+    ) -> Result<bool, FederationError> {
         for (response_name, self_fields) in self.by_response_name.iter() {
             let Some(other_fields) = other.by_response_name.get(response_name) else {
                 continue;
@@ -507,7 +719,7 @@ impl FieldsConflictValidator {
                         if let (Some(self_validator), Some(other_validator)) =
                             (self_validator, other_validator)
                         {
-                            if !self_validator.do_merge_with(other_validator)? {
+                            if !self_validator.do_merge_with(other_validator, cache)? {
                                 return Ok(false);
                             }
                         }
@@ -517,7 +729,7 @@ impl FieldsConflictValidator {
                         if let (Some(self_validator), Some(other_validator)) =
                             (self_validator, other_validator)
                         {
-                            if !self_validator.has_same_response_shape(other_validator)? {
+                            if !self_validator.has_same_response_shape(other_validator, cache)? {
                                 return Ok(false);
                             }
                         }
@@ -529,13 +741,41 @@ impl FieldsConflictValidator {
     }
 
     fn do_merge_with_all<'a>(
-        &self,
-        mut iter: impl Iterator<Item = &'a FieldsConflictValidator>,
+        self: &Arc<Self>,
+        mut iter: impl Iterator<Item = &'a Arc<FieldsConflictValidator>>,
+        cache: &mut FieldsConflictValidatorCache,
     ) -> Result<bool, FederationError> {
-        iter.try_fold(true, |acc, v| Ok(acc && v.do_merge_with(self)?))
+        iter.try_fold(true, |acc, v| Ok(acc && v.do_merge_with(self, cache)?))
     }
 }
 
+/// Memoized results of `FieldsConflictValidator` comparisons, keyed by validator identity.
+///
+/// The same pairs of validators are compared repeatedly when many fragments apply at one level, so
+/// caching the outcome avoids recursing through the nested validators each time. Validators are
+/// keyed by `Arc` pointer, so structurally equal validators in distinct `Arc`s get distinct
+/// entries, which is always correct (if not always a cache hit).
+///
+/// Since a freed allocation can be reused by another validator, this cache must not outlive the
+/// validators it was populated with (see `FragmentRestrictionAtTypeCache`).
+#[derive(Default)]
+struct FieldsConflictValidatorCache {
+    has_same_response_shape: IndexMap<
+        (
+            *const FieldsConflictValidator,
+            *const FieldsConflictValidator,
+        ),
+        bool,
+    >,
+    do_merge_with: IndexMap<
+        (
+            *const FieldsConflictValidator,
+            *const FieldsConflictValidator,
+        ),
+        bool,
+    >,
+}
+
 struct FieldsConflictMultiBranchValidator {
     validators: Vec<Arc<FieldsConflictValidator>>,
     used_spread_trimmed_part_at_level: Vec<Arc<FieldsConflictValidator>>,
@@ -577,6 +817,7 @@ impl FieldsConflictMultiBranchValidator {
     fn check_can_reuse_fragment_and_track_it(
         &mut self,
         fragment_restriction: &FragmentRestrictionAtType,
+        cache: &mut FieldsConflictValidatorCache,
     ) -> Result<bool, FederationError> {
         // No validator means that everything in the fragment selection was part of the selection
         // we're optimizing away (by using the fragment), and we know the original selection was
@@ -585,7 +826,7 @@ impl FieldsConflictMultiBranchValidator {
             return Ok(true); // Nothing to check; Trivially ok.
         };
 
-        if !validator.do_merge_with_all(self.validators.iter().map(Arc::as_ref))? {
+        if !validator.do_merge_with_all(self.validators.iter(), cache)? {
             return Ok(false);
         }
 
@@ -593,8 +834,9 @@ impl FieldsConflictMultiBranchValidator {
         // selection, but also that it merge with any of the trimmed parts of any fragment we have
         // added already.
         // Note: this last condition means that if 2 fragment conflict on their "trimmed" parts,
-        // then the choice of which is used can be based on the fragment ordering and selection
-        // order, which may not be optimal. This feels niche enough that we keep it simple for now,
+        // then the choice of which is used is based on the order fragments are considered in
+        // (see `try_apply_fragments`) and selection order, which is deterministic but may not be
+        // optimal. This feels niche enough that we keep it simple for now,
         // but we can revisit this decision if we run into real cases that justify it (but making
         // it optimal would be a involved in general, as in theory you could have complex
         // dependencies of fragments that conflict, even cycles, and you need to take the size of
@@ -602,11 +844,7 @@ impl FieldsConflictMultiBranchValidator {
         // overall usage, as it can be better to reuse a fragment that is used in other places,
         // than to use one for which it's the only usage. Adding to all that the fact that conflict
         // can happen in sibling branches).
-        if !validator.do_merge_with_all(
-            self.used_spread_trimmed_part_at_level
-                .iter()
-                .map(Arc::as_ref),
-        )? {
+        if !validator.do_merge_with_all(self.used_spread_trimmed_part_at_level.iter(), cache)? {
             return Ok(false);
         }
 
@@ -635,6 +873,9 @@ struct FragmentRestrictionAtType {
 #[derive(Default)]
 struct FragmentRestrictionAtTypeCache {
     map: IndexMap<(Name, CompositeTypeDefinitionPosition), Arc<FragmentRestrictionAtType>>,
+    /// Kept alongside `map`, which holds on to the fragment validators for as long as their
+    /// comparison results are cached.
+    validator_results: FieldsConflictValidatorCache,
 }
 
 impl FragmentRestrictionAtTypeCache {
@@ -718,14 +959,18 @@ impl Fragment {
 
         // Use a smaller validator for efficiency.
         // Note that `trimmed` is the difference of 2 selections that may not have been normalized
-        // on the same parent type, so in practice, it is possible that `trimmed` contains some of
-        // the selections that `selectionSet` contains, but that they have been simplified in
-        // `selectionSet` in such a way that the `minus` call does not see it. However, it is not
-        // trivial to deal with this, and it is fine given that we use trimmed to create the
-        // validator because we know the non-trimmed parts cannot create field conflict issues so
-        // we're trying to build a smaller validator, but it's ok if trimmed is not as small as it
-        // theoretically can be.
-        let trimmed = expanded_selection_set.minus(&selection_set)?;
+        // on the same parent type, so `selection_set` may contain some selections of
+        // `expanded_selection_set` that have been simplified in such a way that a key-based
+        // `minus` does not see them. So, we also subtract `selection_set` re-normalized on the
+        // type of `expanded_selection_set`. It is ok if trimmed is still not as small as it
+        // theoretically can be, as we know the non-trimmed parts cannot create field conflict
+        // issues, and we are only trying to build a smaller validator.
+        let trimmed = expanded_selection_set.minus_with_options(
+            &selection_set,
+            SetOperationOptions {
+                renormalize_other: true,
+            },
+        )?;
         let validator =
             (!trimmed.is_empty()).then(|| FieldsConflictValidator::from_selection_set(&trimmed));
         Ok(FragmentRestrictionAtType::new(
@@ -791,9 +1036,9 @@ impl<'a> FullMatchingFragmentCondition<'a> {
                     return true;
                 }
 
-                // PORT_NOTE: The JS version handles `@defer` directive differently. However, Rust
-                // version can't have `@defer` at this point (see comments on `enum SelectionKey`
-                // definition)
+                // Note that `@defer` can't be on a fragment definition, so if this element is
+                // deferred, the directive is kept on the resulting spread and the defer boundary
+                // is preserved.
                 fragment.type_condition_position == **type_condition_position
                     && fragment
                         .directives
@@ -812,8 +1057,8 @@ enum SelectionSetOrFragment {
 }
 
 impl SelectionSet {
-    /// Reduce the list of applicable fragments by eliminating fragments that directly include
-    /// another fragment.
+    /// Reduce the list of applicable fragments by eliminating fragments that are included in
+    /// another applicable fragment.
     //
     // We have found the list of fragments that applies to some subset of sub-selection. In
     // general, we want to now produce the selection set with spread for those fragments plus
@@ -829,15 +1074,12 @@ impl SelectionSet {
     // use `F1`. So in practice, we filter away any fragment spread that is known to be
     // included in another one that applies.
     //
-    // TODO: note that the logic used for this is theoretically a bit sub-optimal. That is, we
-    // only check if one of the fragment happens to directly include a spread for another
-    // fragment at top-level as in the example above. We do this because it is cheap to check
-    // and is likely the most common case of this kind of inclusion. But in theory, we would
-    // have `fragment F1 on X { a b c }` and `fragment F2 on X { b c }`, in which case `F2` is
-    // still included in `F1`, but we'd have to work harder to figure this out and it's unclear
-    // it's a good tradeoff. And while you could argue that it's on the user to define its
-    // fragments a bit more optimally, it's actually a tad more complex because we're looking
-    // at fragments in a particular context/parent type. Consider an interface `I` and:
+    // Besides the cheap check for a fragment directly spreading another one at top-level, we
+    // also compare the selections of the fragments at the parent type. This catches cases like
+    // `fragment F1 on X { a b c }` and `fragment F2 on X { b c }`, where `F2` is included in `F1`
+    // even though `F1` does not spread it. Since `FragmentRestrictionAtType::selections` are
+    // normalized at the parent type, this also handles inclusions that only hold in a particular
+    // context. Consider an interface `I` and:
     // ```graphql
     //   fragment F3 on I {
     //     ... on X {
@@ -858,25 +1100,42 @@ impl SelectionSet {
     //     }
     //   }
     // ```
-    // In that case, neither fragment include the other per-se. But what if we have
-    // sub-selection `{ b c }` but where parent type is `Y`. In that case, both `F3` and `F4`
-    // applies, and in that particular context, `F3` is fully included in `F4`. Long story
-    // short, we'll currently return `{ ...F3 ...F4 }` in that case, but it would be
-    // technically better to return only `F4`. However, this feels niche, and it might be
-    // costly to verify such inclusions, so not doing it for now.
+    // Neither fragment includes the other per-se. But at parent type `Y`, `F3` restricts to
+    // `{ b c }` and `F4` to `{ c }`, so for a sub-selection like `{ b c e }` at that type, `F4` is
+    // included in `F3` and only `F3` is kept. (The JS comment this is ported from states the
+    // inclusion the other way around, but `F3` is the larger one here.)
     fn reduce_applicable_fragments(
         applicable_fragments: &mut Vec<(Node<Fragment>, Arc<FragmentRestrictionAtType>)>,
     ) {
-        // Note: It's not possible for two fragments to include each other. So, we don't need to
-        //       worry about inclusion cycles.
+        // Note: It's not possible for two fragments to directly include each other. Fragments
+        //       whose selections are equal do cover each other, so we only keep the first one of
+        //       those in order to avoid dropping both.
         let included_fragments: IndexSet<Name> = applicable_fragments
             .iter()
-            .filter(|(fragment, _)| {
-                applicable_fragments
-                    .iter()
-                    .any(|(other_fragment, _)| other_fragment.includes(&fragment.name))
+            .enumerate()
+            .filter(|(index, (fragment, at_type))| {
+                applicable_fragments.iter().enumerate().any(
+                    |(other_index, (other_fragment, other_at_type))| {
+                        if *index == other_index {
+                            return false;
+                        }
+                        if other_fragment.includes(&fragment.name) {
+                            return true;
+                        }
+                        match other_at_type
+                            .selections
+                            .containment(&at_type.selections, Default::default())
+                        {
+                            Containment::StrictlyContained => true,
+                            Containment::Equal => {
+                                other_index < *index && !fragment.includes(&other_fragment.name)
+                            }
+                            Containment::NotContained => false,
+                        }
+                    },
+                )
             })
-            .map(|(fragment, _)| fragment.name.clone())
+            .map(|(_, (fragment, _))| fragment.name.clone())
             .collect();
 
         applicable_fragments.retain(|(fragment, _)| !included_fragments.contains(&fragment.name));
@@ -921,16 +1180,19 @@ impl SelectionSet {
                 continue;
             }
 
-            // I don't love this, but fragments may introduce new fields to the operation, including
-            // fields that use variables that are not declared in the operation. There are two ways
-            // to work around this: adjusting the fragments so they only list the fields that we
-            // actually need, or excluding fragments that introduce variable references from reuse.
-            // The former would be ideal, as we would not execute more fields than required. It's
-            // also much trickier to do. The latter fixes this particular issue but leaves the
-            // output in a less than ideal state.
-            // The consideration here is: `generate_query_fragments` has significant advantages
-            // over fragment reuse, and so we do not want to invest a lot of time into improving
-            // fragment reuse. We do the simple, less-than-ideal thing.
+            // A deferred selection is a hard boundary for fragment reuse: fragments may be reused
+            // inside or outside of it, but never span it. Reusing a fragment that contains a
+            // deferred selection would move that boundary into the fragment definition, so we
+            // skip those.
+            if candidate.has_defer() {
+                continue;
+            }
+
+            // Fragments may introduce new fields to the operation, including fields that use
+            // variables that are not declared in the operation. Those fields are normally trimmed
+            // from the fragments before reusing them (see
+            // `NamedFragments::trim_undeclared_variables`), but as a fallback, we still exclude
+            // any fragment that introduces undeclared variable references from reuse.
             if let Some(variable_definitions) = &context.operation_variables {
                 let fragment_variables = candidate.used_variables();
                 if fragment_variables
@@ -965,7 +1227,10 @@ impl SelectionSet {
             );
             match res {
                 Containment::Equal if full_match_condition.check(candidate) => {
-                    if !validator.check_can_reuse_fragment_and_track_it(&at_type)? {
+                    if !validator.check_can_reuse_fragment_and_track_it(
+                        &at_type,
+                        &mut fragments_at_type.validator_results,
+                    )? {
                         // We cannot use it at all, so no point in adding to `applicable_fragments`.
                         continue;
                     }
@@ -988,25 +1253,50 @@ impl SelectionSet {
             return Ok(self.clone().into()); // Not optimizable
         }
 
+        // When several applicable fragments are equally good (they have equal selections at this
+        // type, or conflict with each other's trimmed parts), the one considered first wins. So,
+        // we consider them in a stable order to make the choice deterministic regardless of the
+        // order the fragments are defined in: larger fragments first, as they save more, then by
+        // name, as names are unique.
+        applicable_fragments.sort_by(|(fragment1, at_type1), (fragment2, at_type2)| {
+            at_type2
+                .selections
+                .selection_count()
+                .cmp(&at_type1.selections.selection_count())
+                .then_with(|| fragment1.name.cmp(&fragment2.name))
+        });
+
         // Narrow down the list of applicable fragments by removing those that are included in
         // another.
         Self::reduce_applicable_fragments(&mut applicable_fragments);
 
-        // Build a new optimized selection set.
+        // Pick the fragments to use.
         let mut not_covered_so_far = self.clone();
-        let mut optimized = SelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        let mut used_fragments: IndexSet<Name> = IndexSet::default();
         for (fragment, at_type) in applicable_fragments {
-            if !validator.check_can_reuse_fragment_and_track_it(&at_type)? {
+            if !validator.check_can_reuse_fragment_and_track_it(
+                &at_type,
+                &mut fragments_at_type.validator_results,
+            )? {
                 continue;
             }
             let not_covered = self.minus(&at_type.selections)?;
             not_covered_so_far = not_covered_so_far.intersection(&not_covered)?;
+            used_fragments.insert(fragment.name.clone());
+        }
 
+        // Build a new optimized selection set, with the fragment spreads in definition order.
+        let mut optimized = SelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        for fragment in context
+            .fragments
+            .iter()
+            .filter(|fragment| used_fragments.contains(&fragment.name))
+        {
             // PORT_NOTE: The JS version uses `parent_type` as the "sourceType", which may be
             //            different from `fragment.type_condition_position`. But, Rust version does
             //            not have "sourceType" field for `FragmentSpreadSelection`.
             let fragment_selection = FragmentSpreadSelection::from_fragment(
-                &fragment,
+                fragment,
                 /*directives*/ &Default::default(),
             );
             optimized.add_local_selection(&fragment_selection.into())?;
@@ -1124,26 +1414,116 @@ impl SelectionSet {
 //  F1 first, and then realize that this increases F2 usages to 2, which means we stop there and keep F2.
 
 impl NamedFragments {
+    /// Checks that the fragments are well-formed with respect to `schema` and each other:
+    /// - Every fragment's type condition is a composite type of `schema`.
+    /// - Every fragment spread in a fragment references a fragment of this set.
+    ///
+    /// ## Errors
+    /// Returns an error naming the first offending fragment, if any.
+    pub(crate) fn validate(&self, schema: &ValidFederationSchema) -> Result<(), FederationError> {
+        for fragment in self.iter() {
+            let type_condition = fragment.type_condition_position.type_name();
+            let is_composite = schema
+                .try_get_type(type_condition.clone())
+                .is_some_and(|ty| CompositeTypeDefinitionPosition::try_from(ty).is_ok());
+            if !is_composite {
+                return Err(FederationError::internal(format!(
+                    "Fragment {} has type condition {type_condition}, which is not a composite type of the schema",
+                    fragment.name
+                )));
+            }
+
+            for used_name in fragment.selection_set.used_fragments().keys() {
+                if !self.contains(used_name) {
+                    return Err(FederationError::internal(format!(
+                        "Fragment {} spreads unknown fragment {used_name}",
+                        fragment.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no fragment references itself, directly or through other fragments.
+    ///
+    /// `reduce`, `map_to_expanded_selection_sets` and `reduce_applicable_fragments` all rely on
+    /// the fragments being topologically sorted, which is impossible if they form a cycle.
+    ///
+    /// ## Errors
+    /// Returns an error naming the fragments that form a cycle, if any.
+    pub(crate) fn validate_acyclic(&self) -> Result<(), FederationError> {
+        fn visit<'a>(
+            name: &'a Name,
+            dependencies: &'a IndexMap<&'a Name, IndexMap<Name, u32>>,
+            visited: &mut IndexSet<&'a Name>,
+            path: &mut IndexSet<&'a Name>,
+        ) -> Result<(), FederationError> {
+            if visited.contains(name) {
+                return Ok(());
+            }
+            if let Some(index) = path.get_index_of(name) {
+                let cycle = path
+                    .iter()
+                    .skip(index)
+                    .chain(std::iter::once(&name))
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(FederationError::internal(format!(
+                    "Named fragments form a cycle: {cycle}"
+                )));
+            }
+            // Spreads of unknown fragments can't form a cycle.
+            let Some(used_fragments) = dependencies.get(name) else {
+                return Ok(());
+            };
+            path.insert(name);
+            for used_name in used_fragments.keys() {
+                visit(used_name, dependencies, visited, path)?;
+            }
+            path.pop();
+            visited.insert(name);
+            Ok(())
+        }
+
+        let dependencies: IndexMap<&Name, IndexMap<Name, u32>> = self
+            .iter()
+            .map(|fragment| (&fragment.name, fragment.selection_set.used_fragments()))
+            .collect();
+        let mut visited = IndexSet::default();
+        let mut path = IndexSet::default();
+        for name in dependencies.keys() {
+            visit(name, &dependencies, &mut visited, &mut path)?;
+        }
+        Ok(())
+    }
+
     /// Updates `self` by computing the reduced set of NamedFragments that are used in the
     /// selection set and other fragments at least `min_usage_to_optimize` times. Also, computes
     /// the new selection set that uses only the reduced set of fragments by expanding the other
     /// ones.
     /// - Returned selection set will be normalized.
-    fn reduce(
+    /// - Returns `Cow::Borrowed` if no fragment was dropped, in which case the selection set is
+    ///   unchanged.
+    fn reduce<'s>(
         &mut self,
-        selection_set: &SelectionSet,
+        selection_set: &'s SelectionSet,
         min_usage_to_optimize: u32,
-    ) -> Result<SelectionSet, FederationError> {
+    ) -> Result<Cow<'s, SelectionSet>, FederationError> {
         // Call `reduce_inner` repeatedly until we reach a fix-point, since newly computed
         // selection set may drop some fragment references due to normalization, which could lead
         // to further reduction.
         // - It is hard to avoid this chain reaction, since we need to account for the effects of
         //   normalization.
         let mut last_size = self.len();
-        let mut last_selection_set = selection_set.clone();
+        let mut last_selection_set = Cow::Borrowed(selection_set);
         while last_size > 0 {
             let new_selection_set =
-                self.reduce_inner(&last_selection_set, min_usage_to_optimize)?;
+                match self.reduce_inner(&last_selection_set, min_usage_to_optimize)? {
+                    Cow::Owned(new_selection_set) => Some(new_selection_set),
+                    Cow::Borrowed(_) => None,
+                };
 
             // Reached a fix-point => stop
             if self.len() == last_size {
@@ -1166,23 +1546,25 @@ impl NamedFragments {
 
             // Prepare the next iteration
             last_size = self.len();
-            last_selection_set = new_selection_set;
+            if let Some(new_selection_set) = new_selection_set {
+                last_selection_set = Cow::Owned(new_selection_set);
+            }
         }
         Ok(last_selection_set)
     }
 
     /// The inner loop body of `reduce` method.
-    fn reduce_inner(
+    fn reduce_inner<'s>(
         &mut self,
-        selection_set: &SelectionSet,
+        selection_set: &'s SelectionSet,
         min_usage_to_optimize: u32,
-    ) -> Result<SelectionSet, FederationError> {
+    ) -> Result<Cow<'s, SelectionSet>, FederationError> {
         let mut usages = selection_set.used_fragments();
 
         // Short-circuiting: Nothing was used => Drop everything (selection_set is unchanged).
         if usages.is_empty() {
             *self = Default::default();
-            return Ok(selection_set.clone());
+            return Ok(Cow::Borrowed(selection_set));
         }
 
         // Determine which one to retain.
@@ -1216,7 +1598,7 @@ impl NamedFragments {
 
         // Short-circuiting: Nothing was dropped (fully used) => Nothing to change.
         if self.len() == original_size {
-            return Ok(selection_set.clone());
+            return Ok(Cow::Borrowed(selection_set));
         }
 
         // Update the fragment definitions in `self` after reduction.
@@ -1245,11 +1627,13 @@ impl NamedFragments {
         // Expanding fragments could create some "inefficiencies" that we wouldn't have if we
         // hadn't re-optimized the fragments to de-optimize it later, so we do a final "flatten"
         // pass to remove those.
-        reduced_selection_set.flatten_unnecessary_fragments(
-            &reduced_selection_set.type_position,
-            self,
-            &selection_set.schema,
-        )
+        reduced_selection_set
+            .flatten_unnecessary_fragments(
+                &reduced_selection_set.type_position,
+                self,
+                &selection_set.schema,
+            )
+            .map(Cow::Owned)
     }
 
     fn update_usages(
@@ -1264,6 +1648,34 @@ impl NamedFragments {
             *usages.entry(name).or_insert(0) += inner_count * usage_count;
         }
     }
+
+    /// Reorders the fragments to follow `original_order` (typically, the order in which they
+    /// were defined in the user's document) where possible.
+    /// - A fragment's dependencies are moved right before it if they would otherwise come after
+    ///   it, so the result is still in dependency order.
+    /// - Fragments missing from `original_order` are kept at the end, in their current order.
+    fn sorted_by_original_order(&self, original_order: &[Name]) -> NamedFragments {
+        fn visit(name: &Name, fragments: &NamedFragments, sorted: &mut NamedFragments) {
+            if sorted.contains(name) {
+                return;
+            }
+            let Some(fragment) = fragments.get(name) else {
+                return;
+            };
+            // Note: This terminates since fragments can't form a cycle (see `validate_acyclic`).
+            for used_name in fragment.selection_set.used_fragments().keys() {
+                visit(used_name, fragments, sorted);
+            }
+            Arc::make_mut(&mut sorted.fragments).insert(name.clone(), fragment.clone());
+        }
+
+        let mut sorted = NamedFragments::default();
+        let remaining = self.iter().map(|fragment| &fragment.name);
+        for name in original_order.iter().chain(remaining) {
+            visit(name, self, &mut sorted);
+        }
+        sorted
+    }
 }
 
 //=============================================================================
@@ -1357,8 +1769,14 @@ impl InlineFragmentSelection {
     ) -> Result<FragmentSelection, FederationError> {
         let optimized;
 
-        let type_condition_position = &self.inline_fragment.type_condition_position;
-        if let Some(type_condition_position) = type_condition_position {
+        // A deferred fragment without type condition still delimits a defer boundary, so we try
+        // to reuse fragments inside of it at its parent type.
+        let type_condition_position = if self.inline_fragment.has_defer() {
+            Some(self.inline_fragment.casted_type())
+        } else {
+            self.inline_fragment.type_condition_position.clone()
+        };
+        if let Some(type_condition_position) = &type_condition_position {
             let opt = self.selection_set.try_apply_fragments(
                 type_condition_position,
                 context,
@@ -1441,6 +1859,21 @@ impl SelectionSet {
         })
     }
 
+    /// Returns the number of selections in this selection set, including nested ones. Fragment
+    /// spreads are counted as a single selection.
+    fn selection_count(&self) -> usize {
+        self.iter()
+            .map(|selection| match selection {
+                Selection::FragmentSpread(_) => 1,
+                _ => {
+                    1 + selection
+                        .selection_set()
+                        .map_or(0, |subselection| subselection.selection_count())
+                }
+            })
+            .sum()
+    }
+
     fn contains_fragment_spread(&self) -> bool {
         self.iter().any(|selection| {
             matches!(selection, Selection::FragmentSpread(_))
@@ -1451,13 +1884,37 @@ impl SelectionSet {
         })
     }
 
+    /// Checks that selection sets are nested at most `max_depth` levels deep.
+    // Note: This is iterative (unlike most selection set traversals), so that it can be used to
+    //       reject pathologically deep selection sets before recursing through them.
+    fn check_depth(&self, max_depth: usize) -> Result<(), FederationError> {
+        let mut stack = vec![(self, 1)];
+        while let Some((selection_set, depth)) = stack.pop() {
+            if depth > max_depth {
+                return Err(FederationError::internal(format!(
+                    "Selection set is nested more than {max_depth} levels deep"
+                )));
+            }
+            stack.extend(
+                selection_set
+                    .iter()
+                    .filter_map(|selection| selection.selection_set())
+                    .map(|subselection| (subselection, depth + 1)),
+            );
+        }
+        Ok(())
+    }
+
     /// ## Errors
-    /// Returns an error if the selection set contains a named fragment spread.
+    /// Returns an error if the selection set contains a named fragment spread, or if it is nested
+    /// more than `context.max_depth` levels deep.
     fn reuse_fragments(&mut self, context: &ReuseContext<'_>) -> Result<(), FederationError> {
         if context.fragments.is_empty() {
             return Ok(());
         }
 
+        self.check_depth(context.max_depth)?;
+
         if self.contains_fragment_spread() {
             return Err(FederationError::internal("reuse_fragments() must only be used on selection sets that do not contain named fragment spreads"));
         }
@@ -1506,38 +1963,109 @@ impl SelectionSet {
     }
 }
 
+/// How `Operation::optimize` introduces named fragments in the operation.
+#[derive(Debug, Clone)]
+pub enum OptimizeMode {
+    /// Reuse the given fragment definitions, keeping those used at least `min_usages` times.
+    ///
+    /// If `fragment_order` is provided, the kept fragments follow that order where possible, for
+    /// instance to match the order of the original document. Otherwise, they are in dependency
+    /// order.
+    ReuseFragments {
+        fragments: NamedFragments,
+        min_usages: u32,
+        fragment_order: Option<Vec<Name>>,
+    },
+    /// Generate new fragment definitions from the inline fragments of the operation, named
+    /// according to `naming`.
+    GenerateFragments { naming: FragmentNaming },
+}
+
+/// Options for `Operation::optimize`.
+#[derive(Debug, Clone)]
+pub struct OptimizeOptions {
+    pub mode: OptimizeMode,
+    /// The maximum nesting depth of the operation's selection sets when reusing fragments.
+    /// Deeper operations are rejected with an error. Defaults to 500 if `None`.
+    pub max_depth: Option<usize>,
+}
+
+/// Statistics about the result of `Operation::optimize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimizeStats {
+    /// The size of the printed operation (including fragment definitions) before optimization.
+    pub size_before: usize,
+    /// The size of the printed operation (including fragment definitions) after optimization.
+    pub size_after: usize,
+    /// The number of provided fragments that are used by the optimized operation.
+    pub fragments_reused: usize,
+    /// The number of fragments generated for the optimized operation.
+    pub fragments_generated: usize,
+    /// The number of selections in the operation (excluding fragment definitions) before
+    /// optimization. A fragment spread counts as a single selection.
+    pub selections_before: usize,
+    /// The number of selections in the operation (excluding fragment definitions) after
+    /// optimization. A fragment spread counts as a single selection.
+    pub selections_after: usize,
+}
+
+impl OptimizeStats {
+    /// The number of bytes saved by the optimization.
+    pub fn bytes_saved(&self) -> usize {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
 impl Operation {
     // PORT_NOTE: The JS version of `reuse_fragments` takes an optional `minUsagesToOptimize` argument.
-    //            However, it's only used in tests. So, it's removed in the Rust version.
+    //            In the Rust version, it is exposed via `reuse_fragments_with_options`.
     const DEFAULT_MIN_USAGES_TO_OPTIMIZE: u32 = 2;
 
     // `fragments` - rebased fragment definitions for the operation's subgraph
     // - `self.selection_set` must be fragment-spread-free.
+    // - `self.selection_set` must be nested at most `max_depth` levels deep.
+    // Returns the number of fragments that ended up being reused.
     fn reuse_fragments_inner(
         &mut self,
         fragments: &NamedFragments,
         min_usages_to_optimize: u32,
-    ) -> Result<(), FederationError> {
+        max_depth: usize,
+    ) -> Result<usize, FederationError> {
         if fragments.is_empty() {
-            return Ok(());
+            return Ok(0);
+        }
+        // The fragments come from a validated document, so these checks only catch bugs in
+        // rebasing. They are too costly to run on every subgraph fetch in release builds.
+        if cfg!(debug_assertions) {
+            fragments.validate(&self.schema)?;
+            fragments.validate_acyclic()?;
+        }
+
+        // Only keep the parts of the fragments that use variables declared by the operation.
+        let operation_variables = self.variables.iter().map(|var| &var.name).collect();
+        let fragments = fragments.trim_undeclared_variables(&operation_variables)?;
+        if fragments.is_empty() {
+            return Ok(0);
         }
 
         // Optimize the operation's selection set by re-using existing fragments.
         let before_optimization = self.selection_set.clone();
-        self.selection_set
-            .reuse_fragments(&ReuseContext::for_operation(fragments, &self.variables))?;
+        self.selection_set.reuse_fragments(
+            &ReuseContext::for_operation(&fragments, &self.variables).with_max_depth(max_depth),
+        )?;
         if before_optimization == self.selection_set {
-            return Ok(());
+            return Ok(0);
         }
 
         // Optimize the named fragment definitions by dropping low-usage ones.
-        let mut final_fragments = fragments.clone();
-        let final_selection_set =
-            final_fragments.reduce(&self.selection_set, min_usages_to_optimize)?;
-
-        self.selection_set = final_selection_set;
+        let mut final_fragments = fragments.into_owned();
+        if let Cow::Owned(final_selection_set) =
+            final_fragments.reduce(&self.selection_set, min_usages_to_optimize)?
+        {
+            self.selection_set = final_selection_set;
+        }
         self.named_fragments = final_fragments;
-        Ok(())
+        Ok(self.named_fragments.len())
     }
 
     /// Optimize the parsed size of the operation by applying fragment spreads. Fragment spreads
@@ -1545,17 +2073,60 @@ impl Operation {
     ///
     /// `fragments` - rebased fragment definitions for the operation's subgraph
     ///
+    /// The operation's selection set must be fragment-spread-free, for instance because its
+    /// fragments were expanded during normalization.
+    ///
+    /// Returns the fragments used by the optimized operation.
+    ///
     // PORT_NOTE: In JS, this function was called "optimize".
-    pub(crate) fn reuse_fragments(
+    pub fn reuse_fragments(
+        &mut self,
+        fragments: &NamedFragments,
+    ) -> Result<&NamedFragments, FederationError> {
+        self.reuse_fragments_with_options(fragments, Self::DEFAULT_MIN_USAGES_TO_OPTIMIZE)?;
+        Ok(&self.named_fragments)
+    }
+
+    /// Same as `reuse_fragments`, but with a configurable minimum number of usages for a fragment
+    /// to be kept in the optimized operation. Fragments used fewer times are expanded in place.
+    ///
+    /// `fragments` - rebased fragment definitions for the operation's subgraph
+    /// `min_usages` - the minimum number of usages for a fragment to be kept
+    pub fn reuse_fragments_with_options(
         &mut self,
         fragments: &NamedFragments,
+        min_usages: u32,
     ) -> Result<(), FederationError> {
-        self.reuse_fragments_inner(fragments, Self::DEFAULT_MIN_USAGES_TO_OPTIMIZE)
+        self.reuse_fragments_inner(fragments, min_usages, ReuseContext::DEFAULT_MAX_DEPTH)?;
+        Ok(())
     }
 
     /// Optimize the parsed size of the operation by generating fragments based on the selections
     /// in the operation.
-    pub(crate) fn generate_fragments(&mut self) -> Result<(), FederationError> {
+    ///
+    /// The operation's selection set must be fragment-spread-free, as the generated fragments
+    /// replace the operation's existing fragment definitions.
+    ///
+    /// Returns the generated fragments.
+    pub fn generate_fragments(&mut self) -> Result<&NamedFragments, FederationError> {
+        self.generate_fragments_with_options(
+            FragmentGenerator::DEFAULT_MIN_SELECTIONS,
+            FragmentGenerator::DEFAULT_MIN_DEPTH,
+            Default::default(),
+        )?;
+        Ok(&self.named_fragments)
+    }
+
+    /// Same as `generate_fragments`, but with configurable thresholds for an inline fragment to be
+    /// extracted into a named fragment. An inline fragment is extracted if it has at least
+    /// `min_selections` direct selections, or if its selections are nested at least `min_depth`
+    /// levels deep. Generated fragments are named according to `naming`.
+    pub fn generate_fragments_with_options(
+        &mut self,
+        min_selections: usize,
+        min_depth: usize,
+        naming: FragmentNaming,
+    ) -> Result<(), FederationError> {
         // Currently, this method simply pulls out every inline fragment into a named fragment. If
         // multiple inline fragments are the same, they use the same named fragment.
         //
@@ -1566,12 +2137,52 @@ impl Operation {
         // When we have more advanced correctness testing, we can add more features to fragment
         // generation, like factoring out partial repeated slices of selection sets or only
         // introducing named fragments for patterns that occur more than once.
-        let mut generator = FragmentGenerator::default();
+        let mut generator = FragmentGenerator::new(min_selections, min_depth, naming);
         generator.visit_selection_set(&mut self.selection_set)?;
         self.named_fragments = generator.into_inner();
         Ok(())
     }
 
+    /// Optimize the parsed size of the operation using named fragments, either by reusing the
+    /// provided ones or by generating new ones, depending on `options.mode`.
+    ///
+    /// This combines `reuse_fragments_with_options` and `generate_fragments` into a single entry
+    /// point, and reports how much the operation shrank.
+    pub fn optimize(&mut self, options: OptimizeOptions) -> Result<OptimizeStats, FederationError> {
+        let mut stats = OptimizeStats {
+            size_before: self.to_string().len(),
+            selections_before: self.selection_set.selection_count(),
+            ..Default::default()
+        };
+        match options.mode {
+            OptimizeMode::ReuseFragments {
+                fragments,
+                min_usages,
+                fragment_order,
+            } => {
+                let max_depth = options.max_depth.unwrap_or(ReuseContext::DEFAULT_MAX_DEPTH);
+                stats.fragments_reused =
+                    self.reuse_fragments_inner(&fragments, min_usages, max_depth)?;
+                if let Some(fragment_order) = fragment_order {
+                    self.named_fragments = self
+                        .named_fragments
+                        .sorted_by_original_order(&fragment_order);
+                }
+            }
+            OptimizeMode::GenerateFragments { naming } => {
+                self.generate_fragments_with_options(
+                    FragmentGenerator::DEFAULT_MIN_SELECTIONS,
+                    FragmentGenerator::DEFAULT_MIN_DEPTH,
+                    naming,
+                )?;
+                stats.fragments_generated = self.named_fragments.len();
+            }
+        }
+        stats.size_after = self.to_string().len();
+        stats.selections_after = self.selection_set.selection_count();
+        Ok(stats)
+    }
+
     /// Used by legacy roundtrip tests.
     /// - This lowers `min_usages_to_optimize` to `1` in order to make it easier to write unit tests.
     #[cfg(test)]
@@ -1579,7 +2190,7 @@ impl Operation {
         &mut self,
         fragments: &NamedFragments,
     ) -> Result<(), FederationError> {
-        self.reuse_fragments_inner(fragments, /*min_usages_to_optimize*/ 1)
+        self.reuse_fragments_with_options(fragments, /*min_usages*/ 1)
     }
 
     // PORT_NOTE: This mirrors the JS version's `Operation.expandAllFragments`. But this method is
@@ -1602,20 +2213,53 @@ impl Operation {
     }
 }
 
-#[derive(Debug, Default)]
+/// How generated fragments are named.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FragmentNaming {
+    /// Names built from the type condition and number of selections of the fragment, numbered
+    /// in the order fragments are generated (`_generated_onA2_0`, ...), like the JS query planner
+    /// names them. Names are only unique within an operation.
+    #[default]
+    Sequential,
+    /// Names derived from a hash of the fragment's type condition and selection set, so that
+    /// identical fragments generated for different operations share the same name.
+    ContentHash,
+}
+
+#[derive(Debug)]
 struct FragmentGenerator {
     fragments: NamedFragments,
+    /// Inline fragments with at least this many direct selections are extracted.
+    min_selections: usize,
+    /// Inline fragments whose selections are nested at least this deep are extracted.
+    min_depth: usize,
+    naming: FragmentNaming,
     // XXX(@goto-bus-stop): This is temporary to support mismatch testing with JS!
     names: IndexMap<(String, usize), usize>,
 }
 
 impl FragmentGenerator {
-    // XXX(@goto-bus-stop): This is temporary to support mismatch testing with JS!
-    // In the future, we will just use `.next_name()`.
-    fn generate_name(&mut self, frag: &InlineFragmentSelection) -> Name {
+    // By default, an inline fragment is extracted if it has more than one selection, or a
+    // single selection with a subselection.
+    const DEFAULT_MIN_SELECTIONS: usize = 2;
+    const DEFAULT_MIN_DEPTH: usize = 2;
+
+    fn new(min_selections: usize, min_depth: usize, naming: FragmentNaming) -> Self {
+        Self {
+            fragments: Default::default(),
+            min_selections,
+            min_depth,
+            naming,
+            names: Default::default(),
+        }
+    }
+
+    /// Returns a name for the next generated fragment, in the format used by the JS query planner,
+    /// so that the plans of both planners can be compared.
+    fn next_name(&mut self, fragment: &InlineFragmentSelection) -> Name {
         use std::fmt::Write as _;
 
-        let type_condition = frag
+        let type_condition = fragment
             .inline_fragment
             .type_condition_position
             .as_ref()
@@ -1623,7 +2267,7 @@ impl FragmentGenerator {
                 || "undefined".to_string(),
                 |condition| condition.to_string(),
             );
-        let selections = frag.selection_set.selections.len();
+        let selections = fragment.selection_set.selections.len();
         let mut name = format!("_generated_on{type_condition}{selections}");
 
         let key = (type_condition, selections);
@@ -1637,19 +2281,65 @@ impl FragmentGenerator {
         Name::new_unchecked(&name)
     }
 
+    /// Returns a name derived from the content of a fragment, which is stable across operations.
+    fn content_hash_name(
+        type_condition_position: &CompositeTypeDefinitionPosition,
+        selection_set: &SelectionSet,
+    ) -> Name {
+        let mut hasher = Sha1::new();
+        hasher.update(type_condition_position.type_name().as_str());
+        hasher.update(" ");
+        hasher.update(selection_set.to_string());
+        let hash = hasher.finalize();
+        // A 64-bit prefix of the hash keeps names reasonably short.
+        Name::new_unchecked(&format!("_{}", hex::encode(&hash[..8])))
+    }
+
+    /// Returns the name to use for a new fragment with the given content.
+    fn generate_name(
+        &mut self,
+        fragment: &InlineFragmentSelection,
+    ) -> Result<Name, FederationError> {
+        match self.naming {
+            FragmentNaming::Sequential => Ok(self.next_name(fragment)),
+            FragmentNaming::ContentHash => {
+                let name = Self::content_hash_name(
+                    &fragment.inline_fragment.casted_type(),
+                    &fragment.selection_set,
+                );
+                // Fragments with the same content are reused rather than generated again, so an
+                // existing fragment with that name can only be a hash collision.
+                if self.fragments.contains(&name) {
+                    return Err(FederationError::internal(format!(
+                        "Generated fragment name {name} collides with another fragment"
+                    )));
+                }
+                Ok(name)
+            }
+        }
+    }
+
     /// Is a selection set worth using for a newly generated named fragment?
-    fn is_worth_using(selection_set: &SelectionSet) -> bool {
-        let mut iter = selection_set.iter();
-        let Some(first) = iter.next() else {
+    fn is_worth_using(&self, selection_set: &SelectionSet) -> bool {
+        if selection_set.is_empty() {
             // An empty selection is not worth using (and invalid!)
             return false;
-        };
-        let Selection::Field(field) = first else {
-            return true;
-        };
-        // If there's more than one selection, or one selection with a subselection,
-        // it's probably worth using
-        iter.next().is_some() || field.selection_set.is_some()
+        }
+        selection_set.selections.len() >= self.min_selections
+            || Self::depth(selection_set) >= self.min_depth
+    }
+
+    /// The number of nested levels of selections in a selection set. A leaf field is 1 level deep,
+    /// and a fragment spread counts as 2 levels, the spread and the fragment's selections.
+    fn depth(selection_set: &SelectionSet) -> usize {
+        selection_set
+            .iter()
+            .map(|selection| match selection {
+                Selection::FragmentSpread(_) => 2,
+                _ => 1 + selection.selection_set().map_or(0, Self::depth),
+            })
+            .max()
+            .unwrap_or_default()
     }
 
     /// Modify the selection set so that eligible inline fragments are moved to named fragment spreads.
@@ -1676,7 +2366,7 @@ impl FragmentGenerator {
                         .add_local_selection(&Selection::FragmentSpread(Arc::clone(frag.get())))?;
                 }
                 SelectionValue::InlineFragment(frag)
-                    if !Self::is_worth_using(&frag.get().selection_set) =>
+                    if !self.is_worth_using(&frag.get().selection_set) =>
                 {
                     new_selection_set
                         .add_local_selection(&Selection::InlineFragment(Arc::clone(frag.get())))?;
@@ -1723,9 +2413,7 @@ impl FragmentGenerator {
                     let existing = if let Some(existing) = existing {
                         existing
                     } else {
-                        // XXX(@goto-bus-stop): This is temporary to support mismatch testing with JS!
-                        // This should be reverted to `self.next_name();` when we're ready.
-                        let name = self.generate_name(candidate.get());
+                        let name = self.generate_name(candidate.get())?;
                         self.fragments.insert(Fragment {
                             schema: selection_set.schema.clone(),
                             name: name.clone(),
@@ -1790,39 +2478,6 @@ mod tests {
         }};
     }
 
-    /// Returns a consistent GraphQL name for the given index.
-    fn fragment_name(mut index: usize) -> Name {
-        /// https://spec.graphql.org/draft/#NameContinue
-        const NAME_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_";
-        /// https://spec.graphql.org/draft/#NameStart
-        const NAME_START_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
-
-        if index < NAME_START_CHARS.len() {
-            Name::new_static_unchecked(&NAME_START_CHARS[index..index + 1])
-        } else {
-            let mut s = String::new();
-
-            let i = index % NAME_START_CHARS.len();
-            s.push(NAME_START_CHARS.as_bytes()[i].into());
-            index /= NAME_START_CHARS.len();
-
-            while index > 0 {
-                let i = index % NAME_CHARS.len();
-                s.push(NAME_CHARS.as_bytes()[i].into());
-                index /= NAME_CHARS.len();
-            }
-
-            Name::new_unchecked(&s)
-        }
-    }
-
-    #[test]
-    fn generated_fragment_names() {
-        assert_eq!(fragment_name(0), "a");
-        assert_eq!(fragment_name(100), "Vb");
-        assert_eq!(fragment_name(usize::MAX), "oS5Uz8g3Iqw");
-    }
-
     #[test]
     fn duplicate_fragment_spreads_after_fragment_expansion() {
         // This is a regression test for FED-290, making sure `make_select` method can handle
@@ -1896,7 +2551,721 @@ mod tests {
     }
 
     #[test]
-    fn optimize_fragments_using_other_fragments_when_possible() {
+    fn reuse_fragments_with_custom_min_usages() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                b: Int
+            }
+        "#;
+
+        let query = r#"
+            fragment F on T {
+                a
+                b
+            }
+
+            query {
+                t {
+                    ...F
+                }
+            }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = operation.expand_all_fragments_and_normalize().unwrap();
+
+        // With the default threshold, a fragment used only once is expanded.
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        {
+          t {
+            a
+            b
+          }
+        }
+        "###);
+
+        let mut optimized = expanded.clone();
+        optimized
+            .reuse_fragments_with_options(&operation.named_fragments, 1)
+            .unwrap();
+        insta::assert_snapshot!(optimized, @r###"
+        fragment F on T {
+          a
+          b
+        }
+
+        {
+          t {
+            ...F
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn optimize_reports_bytes_saved() {
+        let schema_doc = r#"
+            type Query {
+                t1: T
+                t2: T
+            }
+
+            type T {
+                a: Int
+                b: Int
+                c: Int
+            }
+        "#;
+
+        let query = r#"
+            fragment F on T {
+                a
+                b
+                c
+            }
+
+            query {
+                t1 {
+                    ...F
+                }
+                t2 {
+                    ...F
+                }
+            }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = operation.expand_all_fragments_and_normalize().unwrap();
+
+        let mut reused = expanded.clone();
+        let stats = reused
+            .optimize(OptimizeOptions {
+                mode: OptimizeMode::ReuseFragments {
+                    fragments: operation.named_fragments.clone(),
+                    min_usages: 2,
+                    fragment_order: None,
+                },
+                max_depth: None,
+            })
+            .unwrap();
+        assert_eq!(stats.size_before, expanded.to_string().len());
+        assert_eq!(stats.size_after, reused.to_string().len());
+        assert!(stats.bytes_saved() > 0);
+        assert_eq!(stats.fragments_reused, 1);
+        assert_eq!(stats.fragments_generated, 0);
+        // `t1 { a b c } t2 { a b c }` becomes `t1 { ...F } t2 { ...F }`.
+        assert_eq!(stats.selections_before, 8);
+        assert_eq!(stats.selections_after, 4);
+
+        let mut generated = expanded.clone();
+        let stats = generated
+            .optimize(OptimizeOptions {
+                mode: OptimizeMode::GenerateFragments {
+                    naming: Default::default(),
+                },
+                max_depth: None,
+            })
+            .unwrap();
+        // There are no inline fragments to extract.
+        assert_eq!(stats.bytes_saved(), 0);
+        assert_eq!(stats.fragments_generated, 0);
+        assert_eq!(stats.selections_before, stats.selections_after);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_selection_sets() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                t: T
+            }
+        "#;
+
+        let query = r#"
+            fragment F on T {
+                a
+            }
+
+            query {
+                t {
+                    t {
+                        ...F
+                    }
+                }
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+        // Builds `{ t { t { ... { t { ...F } } ... } } }` programmatically, with `levels` nested
+        // `t` fields, since deep operations can't be parsed.
+        let nested = |levels: usize| {
+            let mut operation = parse_operation(&schema, query);
+            let outer_t = operation.selection_set.iter().next().unwrap().clone();
+            let inner_t = outer_t
+                .selection_set()
+                .unwrap()
+                .iter()
+                .next()
+                .unwrap()
+                .clone();
+            let mut selection_set = operation
+                .named_fragments
+                .get("F")
+                .unwrap()
+                .selection_set
+                .clone();
+            for _ in 0..levels {
+                selection_set = SelectionSet::from_selection(
+                    selection_set.type_position.clone(),
+                    inner_t
+                        .with_updated_selection_set(Some(selection_set))
+                        .unwrap(),
+                );
+            }
+            operation.selection_set = SelectionSet::from_selection(
+                operation.selection_set.type_position.clone(),
+                outer_t
+                    .with_updated_selection_set(Some(selection_set))
+                    .unwrap(),
+            );
+            operation
+        };
+
+        let mut operation = nested(100);
+        let fragments = operation.named_fragments.clone();
+        let error = operation
+            .optimize(OptimizeOptions {
+                mode: OptimizeMode::ReuseFragments {
+                    fragments,
+                    min_usages: 1,
+                    fragment_order: None,
+                },
+                max_depth: Some(50),
+            })
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Selection set is nested more than 50 levels deep"),
+            "{error}"
+        );
+
+        // An operation far deeper than the default limit is rejected before anything recurses
+        // through it. Building and dropping it still recurses, so that runs with a large stack.
+        std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(512 * 1024 * 1024)
+                .spawn_scoped(scope, || {
+                    let mut operation = nested(50_000);
+                    let fragments = operation.named_fragments.clone();
+                    let error = operation.reuse_fragments(&fragments).unwrap_err();
+                    assert!(
+                        error
+                            .to_string()
+                            .contains("Selection set is nested more than 500 levels deep"),
+                        "{error}"
+                    );
+                })
+                .unwrap()
+                .join()
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn optimize_preserves_original_fragment_order() {
+        let schema_doc = r#"
+            type Query {
+                t1: T
+                t2: T
+            }
+
+            type T {
+                a: Int
+                b: Int
+                c: Int
+                t: T
+            }
+        "#;
+
+        let query = r#"
+            fragment F3 on T {
+                t {
+                    ...F1
+                }
+                c
+            }
+
+            fragment F2 on T {
+                b
+            }
+
+            fragment F1 on T {
+                a
+            }
+
+            query {
+                t1 {
+                    ...F3
+                }
+                t2 {
+                    ...F2
+                }
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+        let operation = parse_operation(&schema, query);
+        let expanded = operation.expand_all_fragments_and_normalize().unwrap();
+        let original_order: Vec<Name> =
+            ExecutableDocument::parse(schema.schema(), query, "query.graphql")
+                .unwrap()
+                .fragments
+                .keys()
+                .cloned()
+                .collect();
+        let optimize = |fragment_order| {
+            let mut optimized = expanded.clone();
+            optimized
+                .optimize(OptimizeOptions {
+                    mode: OptimizeMode::ReuseFragments {
+                        fragments: operation.named_fragments.clone(),
+                        min_usages: 1,
+                        fragment_order,
+                    },
+                    max_depth: None,
+                })
+                .unwrap();
+            validate_operation(&schema, &optimized.to_string());
+            optimized
+                .named_fragments
+                .iter()
+                .map(|fragment| fragment.name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(optimize(None), ["F2", "F1", "F3"]);
+        // `F1` is moved before `F3`, which uses it.
+        assert_eq!(optimize(Some(original_order)), ["F1", "F3", "F2"]);
+    }
+
+    #[test]
+    fn generate_fragments_with_thresholds() {
+        let schema_doc = r#"
+            type Query {
+                i: I
+            }
+
+            interface I {
+                id: ID!
+            }
+
+            type A implements I {
+                id: ID!
+                a: Int
+                b: Int
+            }
+
+            type B implements I {
+                id: ID!
+                t: T
+            }
+
+            type T {
+                x: Int
+            }
+        "#;
+
+        let query = r#"
+            query {
+                i {
+                    ... on A {
+                        a
+                        b
+                    }
+                    ... on B {
+                        t {
+                            x
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+
+        let mut operation = parse_operation(&schema, query);
+        let generated = operation.generate_fragments().unwrap();
+        assert_eq!(generated.len(), 2);
+        assert!(generated.contains("_generated_onA2_0") && generated.contains("_generated_onB1_0"));
+        insta::assert_snapshot!(operation, @r###"
+        fragment _generated_onA2_0 on A {
+          a
+          b
+        }
+
+        fragment _generated_onB1_0 on B {
+          t {
+            x
+          }
+        }
+
+        {
+          i {
+            ..._generated_onA2_0
+            ..._generated_onB1_0
+          }
+        }
+        "###);
+
+        // Only the wider fragment has enough selections, and neither is deep enough.
+        let mut operation = parse_operation(&schema, query);
+        operation
+            .generate_fragments_with_options(2, 3, FragmentNaming::Sequential)
+            .unwrap();
+        insta::assert_snapshot!(operation, @r###"
+        fragment _generated_onA2_0 on A {
+          a
+          b
+        }
+
+        {
+          i {
+            ..._generated_onA2_0
+            ... on B {
+              t {
+                x
+              }
+            }
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn generate_fragments_with_content_hash_names() {
+        let schema_doc = r#"
+            type Query {
+                i: I
+                j: I
+            }
+
+            interface I {
+                id: ID!
+            }
+
+            type A implements I {
+                id: ID!
+                a: Int
+                b: Int
+            }
+
+            type B implements I {
+                id: ID!
+                c: Int
+                d: Int
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+        // Returns the names of the generated fragments, in the order they were generated.
+        let generate = |query: &str, naming| {
+            let mut operation = parse_operation(&schema, query);
+            operation
+                .generate_fragments_with_options(2, 2, naming)
+                .unwrap();
+            validate_operation(&schema, &operation.to_string());
+            operation
+                .named_fragments
+                .iter()
+                .map(|fragment| fragment.name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // The `... on A { a b }` fragment is generated last in both operations.
+        let query1 = "{ i { ... on A { a b } } }";
+        let query2 = "{ j { ... on A { id a } } i { ... on A { a b } } }";
+
+        let sequential1 = generate(query1, FragmentNaming::Sequential);
+        let sequential2 = generate(query2, FragmentNaming::Sequential);
+        assert_eq!(sequential1, ["_generated_onA2_0"]);
+        assert_eq!(sequential2, ["_generated_onA2_0", "_generated_onA2_1"]);
+
+        // The same fragment gets the same name in both operations.
+        let hashed1 = generate(query1, FragmentNaming::ContentHash);
+        let hashed2 = generate(query2, FragmentNaming::ContentHash);
+        assert_eq!(hashed1.last(), hashed2.last());
+        assert_ne!(hashed2[0], hashed2[1]);
+        assert!(hashed1[0].starts_with('_'));
+    }
+
+    #[test]
+    fn minus_with_renormalized_other() {
+        let schema_doc = r#"
+            type Query {
+                i: I
+            }
+
+            interface I {
+                a: Int
+            }
+
+            type A implements I {
+                a: Int
+                x: Int
+            }
+        "#;
+
+        let query = r#"
+            fragment F on I {
+                a
+                ... on A {
+                    x
+                }
+            }
+
+            query {
+                i {
+                    ...F
+                }
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+        let operation = parse_operation(&schema, query);
+        let expanded = operation
+            .named_fragments
+            .get("F")
+            .unwrap()
+            .selection_set
+            .clone();
+        let type_a: CompositeTypeDefinitionPosition = schema
+            .get_type(apollo_compiler::name!("A"))
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let at_a = expanded
+            .flatten_unnecessary_fragments(&type_a, &Default::default(), &schema)
+            .unwrap();
+
+        // `... on A { x }` is flattened into `x` at type `A`, so a key-based difference misses it.
+        insta::assert_snapshot!(expanded.minus(&at_a).unwrap(), @"{ ... on A { x } }");
+        let trimmed = expanded
+            .minus_with_options(
+                &at_a,
+                SetOperationOptions {
+                    renormalize_other: true,
+                },
+            )
+            .unwrap();
+        assert!(trimmed.is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "minus() must only be used on selection sets that do not contain named fragment spreads"
+    )]
+    fn minus_rejects_fragment_spreads() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                b: Int
+            }
+        "#;
+
+        let query = r#"
+            fragment F on T {
+                a
+            }
+
+            query {
+                t {
+                    ...F
+                    b
+                }
+            }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let t = operation.selection_set.iter().next().unwrap();
+        let with_spread = t.selection_set().unwrap();
+        let _ = with_spread.minus(&operation.named_fragments.get("F").unwrap().selection_set);
+    }
+
+    #[test]
+    fn detects_cyclic_fragments() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                t: T
+            }
+        "#;
+
+        let query = r#"
+            fragment F1 on T {
+                t {
+                    ...F2
+                }
+            }
+
+            fragment F2 on T {
+                a
+            }
+
+            query {
+                t {
+                    ...F1
+                }
+            }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let mut fragments = operation.named_fragments.clone();
+        fragments.validate_acyclic().unwrap();
+
+        // Make `F2` spread `F1`, which GraphQL validation would normally reject.
+        let f1 = fragments.get("F1").unwrap().clone();
+        let f2 = fragments.get("F2").unwrap();
+        let cyclic_f2 = Fragment {
+            selection_set: SelectionSet::from_selection(
+                f2.type_condition_position.clone(),
+                FragmentSpreadSelection::from_fragment(&f1, &Default::default()).into(),
+            ),
+            ..Fragment::clone(f2)
+        };
+        fragments.insert(cyclic_f2);
+
+        let error = fragments.validate_acyclic().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Named fragments form a cycle: F2 -> F1 -> F2"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn validates_named_fragments() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                t: T
+            }
+        "#;
+
+        let query = r#"
+            fragment F1 on T {
+                t {
+                    ...F2
+                }
+            }
+
+            fragment F2 on T {
+                a
+            }
+
+            query {
+                t {
+                    ...F1
+                }
+            }
+        "#;
+
+        let schema = parse_schema(schema_doc);
+        let operation = parse_operation(&schema, query);
+        let mut fragments = operation.named_fragments.clone();
+        fragments.validate(&schema).unwrap();
+
+        let other_schema = parse_schema("type Query { a: Int }");
+        let error = fragments.validate(&other_schema).unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "Fragment F2 has type condition T, which is not a composite type of the schema"
+            ),
+            "{error}"
+        );
+
+        fragments.retain(|name, _| name != "F2");
+        let error = fragments.validate(&schema).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Fragment F1 spreads unknown fragment F2"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn caches_validator_comparisons_by_identity() {
+        let schema_doc = r#"
+            type Query {
+                t: T
+            }
+
+            type T {
+                a: Int
+                b: Int
+                t: T
+            }
+        "#;
+
+        let query = r#"
+            query {
+                t {
+                    a
+                    t {
+                        b
+                    }
+                }
+            }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let validator = || {
+            Arc::new(FieldsConflictValidator::from_selection_set(
+                &operation.selection_set,
+            ))
+        };
+        let (v1, v2, v3) = (validator(), validator(), validator());
+        let mut cache = FieldsConflictValidatorCache::default();
+
+        assert!(v1.do_merge_with(&v2, &mut cache).unwrap());
+        let cached = cache.do_merge_with.len();
+        assert!(v1.do_merge_with(&v2, &mut cache).unwrap());
+        assert_eq!(cache.do_merge_with.len(), cached);
+
+        // `v3` is structurally equal to `v2`, but is a distinct `Arc`.
+        assert!(v1.do_merge_with(&v3, &mut cache).unwrap());
+        assert!(cache.do_merge_with.len() > cached);
+        assert!(cache
+            .do_merge_with
+            .contains_key(&(Arc::as_ptr(&v1), Arc::as_ptr(&v3))));
+    }
+
+    #[test]
+    fn optimize_fragments_using_other_fragments_when_possible() {
         let schema = r#"
               type Query {
                 t: I
@@ -2565,6 +3934,381 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn drops_fragments_included_in_another_applicable_fragment() {
+        let schema_doc = r#"
+              type Query {
+                t1: T
+                t2: T
+              }
+
+              type T {
+                a: Int
+                b: Int
+                c: Int
+                d: Int
+              }
+        "#;
+
+        // `F2` is not spread by `F1`, but its selections are still a subset of `F1`'s. So, using
+        // `F1` alone is enough.
+        let query = r#"
+              fragment F1 on T {
+                a
+                b
+                c
+              }
+
+              fragment F2 on T {
+                b
+                c
+              }
+
+              {
+                t1 {
+                  ...F1
+                  ...F2
+                  d
+                }
+                t2 {
+                  ...F1
+                  ...F2
+                  d
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          t1 {
+            a
+            b
+            c
+            d
+          }
+          t2 {
+            a
+            b
+            c
+            d
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment F1 on T {
+          a
+          b
+          c
+        }
+
+        {
+          t1 {
+            ...F1
+            d
+          }
+          t2 {
+            ...F1
+            d
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn picks_equal_fragments_by_name() {
+        let schema_doc = r#"
+              type Query {
+                t1: T
+                t2: T
+              }
+
+              type T {
+                a: Int
+                b: Int
+                c: Int
+              }
+        "#;
+
+        // `B` and `A` have the same selections, so either could be used. `A` wins because of its
+        // name, even though `B` is defined first.
+        let query = r#"
+              fragment B on T {
+                a
+                b
+              }
+
+              fragment A on T {
+                a
+                b
+              }
+
+              {
+                t1 {
+                  ...B
+                  c
+                }
+                t2 {
+                  ...A
+                  c
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          t1 {
+            a
+            b
+            c
+          }
+          t2 {
+            a
+            b
+            c
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment A on T {
+          a
+          b
+        }
+
+        {
+          t1 {
+            ...A
+            c
+          }
+          t2 {
+            ...A
+            c
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn drops_fragments_included_in_another_only_at_parent_type() {
+        let schema_doc = r#"
+              type Query {
+                y1: Y
+                y2: Y
+              }
+
+              interface I {
+                id: ID
+              }
+
+              type X implements I {
+                id: ID
+                a: Int
+              }
+
+              type Y implements I {
+                id: ID
+                b: Int
+                c: Int
+                e: Int
+              }
+
+              type Z implements I {
+                id: ID
+                d: Int
+              }
+        "#;
+
+        // Neither `F3` nor `F4` includes the other, but at type `Y`, `F3` is `{ b c }` and `F4` is
+        // `{ c }`. So, `F3` alone is enough.
+        let query = r#"
+              fragment F3 on I {
+                ... on X {
+                  a
+                }
+                ... on Y {
+                  b
+                  c
+                }
+              }
+
+              fragment F4 on I {
+                ... on Y {
+                  c
+                }
+                ... on Z {
+                  d
+                }
+              }
+
+              {
+                y1 {
+                  ...F3
+                  e
+                }
+                y2 {
+                  ...F4
+                  b
+                  e
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          y1 {
+            b
+            c
+            e
+          }
+          y2 {
+            c
+            b
+            e
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment F3 on I {
+          ... on X {
+            a
+          }
+          ... on Y {
+            b
+            c
+          }
+        }
+
+        {
+          y1 {
+            ...F3
+            e
+          }
+          y2 {
+            ...F3
+            e
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn keeps_the_including_fragment_whatever_the_definition_order() {
+        let schema_doc = r#"
+              type Query {
+                y1: Y
+                y2: Y
+              }
+
+              interface I {
+                id: ID
+              }
+
+              type X implements I {
+                id: ID
+                a: Int
+              }
+
+              type Y implements I {
+                id: ID
+                b: Int
+                c: Int
+                e: Int
+              }
+
+              type Z implements I {
+                id: ID
+                d: Int
+              }
+        "#;
+
+        // Same as `drops_fragments_included_in_another_only_at_parent_type`, but with `F4` defined
+        // first: `F3` is still the one kept, as `F4` is included in it at type `Y`.
+        let query = r#"
+              fragment F4 on I {
+                ... on Y {
+                  c
+                }
+                ... on Z {
+                  d
+                }
+              }
+
+              fragment F3 on I {
+                ... on X {
+                  a
+                }
+                ... on Y {
+                  b
+                  c
+                }
+              }
+
+              {
+                y1 {
+                  ...F3
+                  e
+                }
+                y2 {
+                  ...F4
+                  b
+                  e
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          y1 {
+            b
+            c
+            e
+          }
+          y2 {
+            c
+            b
+            e
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment F3 on I {
+          ... on X {
+            a
+          }
+          ... on Y {
+            b
+            c
+          }
+        }
+
+        {
+          y1 {
+            ...F3
+            e
+          }
+          y2 {
+            ...F3
+            e
+          }
+        }
+        "###);
+    }
+
     #[test]
     fn fragments_application_makes_type_condition_trivial() {
         let schema_doc = r#"
@@ -2773,6 +4517,96 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn handles_interface_fragments_used_at_union_of_implementations() {
+        let schema_doc = r#"
+              type Query {
+                u1: U
+                u2: U
+              }
+
+              interface I {
+                id: ID!
+              }
+
+              type A implements I {
+                id: ID!
+                a: Int
+              }
+
+              type B implements I {
+                id: ID!
+                b: Int
+              }
+
+              union U = A | B
+        "#;
+
+        let query = r#"
+              fragment F on I {
+                ... on A {
+                  a
+                }
+                ... on B {
+                  b
+                }
+              }
+
+              {
+                u1 {
+                  ...F
+                }
+                u2 {
+                  ...F
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(schema_doc), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          u1 {
+            ... on A {
+              a
+            }
+            ... on B {
+              b
+            }
+          }
+          u2 {
+            ... on A {
+              a
+            }
+            ... on B {
+              b
+            }
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment F on I {
+          ... on A {
+            a
+          }
+          ... on B {
+            b
+          }
+        }
+
+        {
+          u1 {
+            ...F
+          }
+          u2 {
+            ...F
+          }
+        }
+        "###);
+    }
+
     #[test]
     fn handles_fragments_on_union_in_context_with_limited_intersection() {
         let schema_doc = r#"
@@ -3353,6 +5187,204 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn trims_fragment_fields_using_undeclared_variables() {
+        let schema_doc = r#"
+              type Query {
+                t1: T
+                t2: T
+              }
+
+              type T {
+                a: Int
+                b(arg: Int): Int
+                c: Int
+              }
+        "#;
+
+        // `F` uses `$v`, which `Q2` does not declare. The fields using it are trimmed from `F`, so
+        // the rest of the fragment can still be reused.
+        let query = r#"
+              query Q1($v: Int) {
+                t1 {
+                  ...F
+                }
+              }
+
+              query Q2 {
+                t1 {
+                  a
+                  c
+                }
+                t2 {
+                  a
+                  c
+                }
+              }
+
+              fragment F on T {
+                a
+                b(arg: $v)
+                c
+              }
+        "#;
+
+        let operation =
+            Operation::parse(parse_schema(schema_doc), query, "query.graphql", Some("Q2")).unwrap();
+        assert_optimized!(operation, operation.named_fragments, @r###"
+        fragment F on T {
+          a
+          c
+        }
+
+        query Q2 {
+          t1 {
+            ...F
+          }
+          t2 {
+            ...F
+          }
+        }
+        "###);
+    }
+
+    const DEFER_SCHEMA: &str = r#"
+          directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+          type Query {
+            t1: T
+            t2: T
+          }
+
+          type T {
+            a: Int
+            b: Int
+            c: Int
+          }
+    "#;
+
+    #[test]
+    fn reuse_fragments_inside_and_outside_of_defer() {
+        let query = r#"
+              fragment F on T {
+                a
+                b
+              }
+
+              {
+                t1 {
+                  ...F
+                  ... @defer {
+                    c
+                  }
+                }
+                t2 {
+                  ... @defer {
+                    ...F
+                  }
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(DEFER_SCHEMA), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          t1 {
+            a
+            b
+            ... @defer {
+              c
+            }
+          }
+          t2 {
+            ... @defer {
+              a
+              b
+            }
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        fragment F on T {
+          a
+          b
+        }
+
+        {
+          t1 {
+            ...F
+            ... @defer {
+              c
+            }
+          }
+          t2 {
+            ...F @defer
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn does_not_reuse_fragments_spanning_a_defer_boundary() {
+        let query = r#"
+              fragment F on T {
+                a
+                ... @defer {
+                  b
+                }
+              }
+
+              {
+                t1 {
+                  ...F
+                }
+                t2 {
+                  ...F
+                }
+              }
+        "#;
+
+        let operation = parse_operation(&parse_schema(DEFER_SCHEMA), query);
+        let expanded = assert_without_fragments!(
+            operation,
+            @r###"
+        {
+          t1 {
+            a
+            ... @defer {
+              b
+            }
+          }
+          t2 {
+            a
+            ... @defer {
+              b
+            }
+          }
+        }
+        "###
+        );
+        assert_optimized!(expanded, operation.named_fragments, @r###"
+        {
+          t1 {
+            a
+            ... @defer {
+              b
+            }
+          }
+          t2 {
+            a
+            ... @defer {
+              b
+            }
+          }
+        }
+        "###);
+    }
+
     #[test]
     fn reuse_fragments_with_non_intersecting_types() {
         let schema = r#"
@@ -306,9 +306,8 @@ impl InlineFragmentSelection {
                 let rebased_inline_fragment =
                     self.inline_fragment.rebase_on(parent_type, schema)?;
 
-                let mut nonliftable_selections = selection_set.selections.clone();
-                Arc::make_mut(&mut nonliftable_selections)
-                    .retain(|k, _| !liftable_selections.contains_key(k));
+                let nonliftable_selections =
+                    Arc::new(selection_set.selections.difference(&liftable_selections));
 
                 let rebased_casted_type = rebased_inline_fragment.casted_type();
                 let final_inline_fragment: Selection = InlineFragmentSelection::new(
@@ -324,7 +323,7 @@ impl InlineFragmentSelection {
                 // Since liftable_selections are changing their parent, we need to rebase them.
                 liftable_selections = liftable_selections
                     .into_iter()
-                    .map(|(_key, sel)| sel.rebase_on(parent_type, named_fragments, schema))
+                    .map(|sel| sel.rebase_on(parent_type, named_fragments, schema))
                     .collect::<Result<_, _>>()?;
 
                 let mut final_selection_map = SelectionMap::new();
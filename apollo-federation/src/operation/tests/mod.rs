@@ -1,9 +1,12 @@
+use std::sync::Arc;
+
 use apollo_compiler::collections::IndexSet;
 use apollo_compiler::name;
 use apollo_compiler::schema::Schema;
 use apollo_compiler::ExecutableDocument;
 
 use super::normalize_operation;
+use super::FragmentSpreadSelection;
 use super::Name;
 use super::NamedFragments;
 use super::Operation;
@@ -1710,3 +1713,214 @@ fn directive_propagation() {
     .expect_err("directive @fragSpreadOnly to be rejected");
     insta::assert_snapshot!(err, @"Unsupported custom directive @fragSpreadOnly on fragment spread. Due to query transformations during planning, the router requires directives on fragment spreads to support both the FRAGMENT_SPREAD and INLINE_FRAGMENT locations.");
 }
+
+const SAMPLE_OPERATION_DOC: &str = r#"
+    type Query {
+        foo: Foo!
+    }
+
+    type Foo {
+        a: Int!
+        b: Int!
+        c: Int!
+        d: Int!
+    }
+
+    query TestQuery {
+        foo {
+            a
+            b
+            c
+            d
+        }
+    }
+    "#;
+
+fn foo_selections() -> SelectionSet {
+    let (schema, executable_document) = parse_schema_and_operation(SAMPLE_OPERATION_DOC);
+    let operation = normalize_operation(
+        executable_document.operations.get(None).unwrap(),
+        Default::default(),
+        &schema,
+        &Default::default(),
+    )
+    .unwrap();
+    get_value_at_path(&operation.selection_set, &[name!("foo")])
+        .and_then(|foo| foo.selection_set())
+        .expect("foo should have a selection set")
+        .clone()
+}
+
+fn field_key(name: Name) -> SelectionKey {
+    SelectionKey::Field {
+        response_name: name,
+        directives: Default::default(),
+    }
+}
+
+fn selection_strings(selection_set: &SelectionSet) -> Vec<String> {
+    selection_set
+        .selections
+        .values()
+        .map(|selection| selection.to_string())
+        .collect()
+}
+
+#[test]
+fn remove_preserves_order() {
+    let mut selection_set = foo_selections();
+    let selections = Arc::make_mut(&mut selection_set.selections);
+    let (index, removed) = selections.remove(&field_key(name!("b"))).unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(removed.to_string(), "b");
+    assert!(selections.remove(&field_key(name!("b"))).is_none());
+    assert_eq!(selection_strings(&selection_set), ["a", "c", "d"]);
+}
+
+#[test]
+fn into_iter_yields_selections() {
+    let selection_set = foo_selections();
+    let mut borrowed = Vec::new();
+    for selection in &*selection_set.selections {
+        borrowed.push(selection.to_string());
+    }
+    assert_eq!(borrowed, ["a", "b", "c", "d"]);
+
+    let owned: Vec<Selection> = (*selection_set.selections).clone().into_iter().collect();
+    assert_eq!(owned.len(), 4);
+    assert_eq!(owned[3].to_string(), "d");
+}
+
+#[test]
+fn insert_merged_merges_sub_selections() {
+    let schema = parse_schema(
+        r#"
+        type Query {
+            foo: Foo!
+        }
+
+        type Foo {
+            a: Int!
+            b: Int!
+        }
+        "#,
+    );
+    let mut operation = parse_operation(&schema, "{ foo { a } }");
+    let other = parse_operation(&schema, "{ foo { b } }");
+    let other_foo = other.selection_set.selections.values().next().unwrap();
+
+    let selections = Arc::make_mut(&mut operation.selection_set.selections);
+    selections.insert_merged(other_foo).unwrap();
+    assert_eq!(operation.selection_set.to_string(), "{ foo { a b } }");
+
+    // `insert` replaces the existing selection instead.
+    let selections = Arc::make_mut(&mut operation.selection_set.selections);
+    selections.insert(other_foo.clone());
+    assert_eq!(operation.selection_set.to_string(), "{ foo { b } }");
+}
+
+#[test]
+fn filter_recursive_depth_first_recurses_into_fragment_spreads() {
+    let schema = parse_schema(
+        r#"
+        type Query {
+            foo: Foo!
+        }
+
+        type Foo {
+            a: Int!
+            b: Int!
+        }
+        "#,
+    );
+    let operation = parse_operation(&schema, "{ foo { ...F } } fragment F on Foo { a b }");
+    let fragment = operation.named_fragments.get("F").unwrap();
+    let spread = Selection::FragmentSpread(Arc::new(FragmentSpreadSelection::from_fragment(
+        fragment,
+        &Default::default(),
+    )));
+    let selection_set =
+        SelectionSet::from_selection(fragment.type_condition_position.clone(), spread);
+
+    let mut visited = Vec::new();
+    let filtered = selection_set
+        .filter_recursive_depth_first(&mut |selection| {
+            visited.push(selection.to_string());
+            Ok(!matches!(selection, Selection::Field(field) if *field.field.name() == name!("b")))
+        })
+        .unwrap();
+    assert_eq!(visited, ["a", "b", "...F"]);
+
+    let Some(Selection::FragmentSpread(filtered_spread)) = filtered.selections.values().next()
+    else {
+        panic!("fragment spread should be kept");
+    };
+    assert_eq!(filtered_spread.spread.fragment_name, name!("F"));
+    assert_eq!(filtered_spread.selection_set.to_string(), "{ a }");
+}
+
+#[test]
+fn key_only_set_operations() {
+    let schema = parse_schema(
+        r#"
+        type Query {
+            foo: Foo!
+            a: Int!
+        }
+
+        type Foo {
+            a: Int!
+            b: Int!
+        }
+        "#,
+    );
+    let left = parse_operation(&schema, "{ a foo { a } }");
+    let right = parse_operation(&schema, "{ foo { b } }");
+    let left = &left.selection_set.selections;
+    let right = &right.selection_set.selections;
+
+    let difference: Vec<_> = left
+        .difference(right)
+        .values()
+        .map(|s| s.to_string())
+        .collect();
+    // Sub-selections are not compared: `foo` is dropped although it selects a different field.
+    assert_eq!(difference, ["a"]);
+
+    // Sub-selections are not compared: `foo` is kept as it is in `left`.
+    let intersection: Vec<_> = left
+        .intersection(right)
+        .values()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(intersection, ["foo { a }"]);
+}
+
+#[test]
+fn try_insert_rejects_duplicate_keys() {
+    let schema = parse_schema(
+        r#"
+        type Query {
+            foo: Foo!
+        }
+
+        type Foo {
+            a: Int!
+            b: Int!
+        }
+        "#,
+    );
+    let mut operation = parse_operation(&schema, "{ foo { a } }");
+    let other = parse_operation(&schema, "{ foo { b } }");
+    let other_foo = other.selection_set.selections.values().next().unwrap();
+
+    let selections = Arc::make_mut(&mut operation.selection_set.selections);
+    let error = selections.try_insert(other_foo.clone()).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("selection foo { a } already exists"),
+        "{error}"
+    );
+    assert_eq!(operation.selection_set.to_string(), "{ foo { a } }");
+}
@@ -390,7 +390,7 @@ impl FetchDependencyGraphProcessor<Option<PlanNode>, DeferredDeferBlock>
                 defer_info
                     .sub_selection
                     .without_empty_branches()?
-                    .map(|filtered| filtered.as_ref().try_into())
+                    .map(|filtered| (&filtered).try_into())
                     .transpose()?
             } else {
                 None
@@ -409,7 +409,7 @@ impl FetchDependencyGraphProcessor<Option<PlanNode>, DeferredDeferBlock>
             primary: PrimaryDeferBlock {
                 sub_selection: sub_selection
                     .without_empty_branches()?
-                    .map(|filtered| filtered.as_ref().try_into())
+                    .map(|filtered| (&filtered).try_into())
                     .transpose()?,
                 node: main.map(Box::new),
             },
@@ -2171,6 +2171,30 @@ impl ObjectFieldArgumentDefinitionPosition {
         Ok(())
     }
 
+    pub(crate) fn insert_directive(
+        &self,
+        schema: &mut FederationSchema,
+        directive: Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let argument = self.make_mut(&mut schema.schema)?;
+        if argument
+            .directives
+            .iter()
+            .any(|other_directive| other_directive.ptr_eq(&directive))
+        {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Directive application \"@{}\" already exists on object field argument \"{}\"",
+                    directive.name, self,
+                ),
+            }
+            .into());
+        }
+        let name = directive.name.clone();
+        argument.make_mut().directives.push(directive);
+        self.insert_directive_name_references(&mut schema.referencers, &name)
+    }
+
     /// Remove a directive application from this position by name.
     pub(crate) fn remove_directive_name(&self, schema: &mut FederationSchema, name: &str) {
         let Some(argument) = self.try_make_mut(&mut schema.schema) else {
@@ -3169,6 +3193,30 @@ impl InterfaceFieldArgumentDefinitionPosition {
         Ok(())
     }
 
+    pub(crate) fn insert_directive(
+        &self,
+        schema: &mut FederationSchema,
+        directive: Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let argument = self.make_mut(&mut schema.schema)?;
+        if argument
+            .directives
+            .iter()
+            .any(|other_directive| other_directive.ptr_eq(&directive))
+        {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Directive application \"@{}\" already exists on interface field argument \"{}\"",
+                    directive.name, self,
+                ),
+            }
+            .into());
+        }
+        let name = directive.name.clone();
+        argument.make_mut().directives.push(directive);
+        self.insert_directive_name_references(&mut schema.referencers, &name)
+    }
+
     /// Remove a directive application from this position by name.
     pub(crate) fn remove_directive_name(&self, schema: &mut FederationSchema, name: &str) {
         let Some(argument) = self.try_make_mut(&mut schema.schema) else {
@@ -4117,6 +4165,30 @@ impl EnumValueDefinitionPosition {
         Ok(())
     }
 
+    pub(crate) fn insert_directive(
+        &self,
+        schema: &mut FederationSchema,
+        directive: Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let value = self.make_mut(&mut schema.schema)?;
+        if value
+            .directives
+            .iter()
+            .any(|other_directive| other_directive.ptr_eq(&directive))
+        {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Directive application \"@{}\" already exists on enum value \"{}\"",
+                    directive.name, self,
+                ),
+            }
+            .into());
+        }
+        let name = directive.name.clone();
+        value.make_mut().directives.push(directive);
+        self.insert_directive_name_references(&mut schema.referencers, &name)
+    }
+
     /// Remove a directive application from this position by name.
     pub(crate) fn remove_directive_name(&self, schema: &mut FederationSchema, name: &str) {
         let Some(value) = self.try_make_mut(&mut schema.schema) else {
@@ -4624,6 +4696,30 @@ impl InputObjectFieldDefinitionPosition {
         Ok(())
     }
 
+    pub(crate) fn insert_directive(
+        &self,
+        schema: &mut FederationSchema,
+        directive: Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let field = self.make_mut(&mut schema.schema)?;
+        if field
+            .directives
+            .iter()
+            .any(|other_directive| other_directive.ptr_eq(&directive))
+        {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Directive application \"@{}\" already exists on input object field \"{}\"",
+                    directive.name, self,
+                ),
+            }
+            .into());
+        }
+        let name = directive.name.clone();
+        field.make_mut().directives.push(directive);
+        self.insert_directive_name_references(&mut schema.referencers, &name)
+    }
+
     /// Remove a directive application from this position by name.
     pub(crate) fn remove_directive_name(&self, schema: &mut FederationSchema, name: &str) {
         let Some(field) = self.try_make_mut(&mut schema.schema) else {
@@ -1,6 +1,7 @@
 mod schema;
 mod subgraph;
 
+use std::fmt::Display;
 use std::fmt::Write;
 use std::ops::Deref;
 use std::ops::Not;
@@ -47,9 +48,17 @@ pub use self::subgraph::ValidFederationSubgraphs;
 use crate::error::FederationError;
 use crate::error::MultipleFederationErrors;
 use crate::error::SingleFederationError;
+use crate::link::argument::directive_required_string_argument;
 use crate::link::cost_spec_definition::CostSpecDefinition;
+use crate::link::cost_spec_definition::COST_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::cost_spec_definition::LIST_SIZE_DIRECTIVE_NAME_IN_SPEC;
 use crate::link::federation_spec_definition::get_federation_spec_definition_from_subgraph;
 use crate::link::federation_spec_definition::FederationSpecDefinition;
+use crate::link::federation_spec_definition::FEDERATION_AUTHENTICATED_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_POLICY_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_REQUIRES_SCOPES_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC;
 use crate::link::federation_spec_definition::FEDERATION_VERSIONS;
 use crate::link::join_spec_definition::FieldDirectiveArguments;
 use crate::link::join_spec_definition::JoinSpecDefinition;
@@ -66,6 +75,7 @@ use crate::schema::position::EnumTypeDefinitionPosition;
 use crate::schema::position::FieldDefinitionPosition;
 use crate::schema::position::InputObjectFieldDefinitionPosition;
 use crate::schema::position::InputObjectTypeDefinitionPosition;
+use crate::schema::position::InterfaceFieldDefinitionPosition;
 use crate::schema::position::InterfaceTypeDefinitionPosition;
 use crate::schema::position::ObjectFieldDefinitionPosition;
 use crate::schema::position::ObjectOrInterfaceFieldDefinitionPosition;
@@ -90,6 +100,35 @@ use crate::utils::FallibleIterator;
 pub(crate) fn extract_subgraphs_from_supergraph(
     supergraph_schema: &FederationSchema,
     validate_extracted_subgraphs: Option<bool>,
+) -> Result<ValidFederationSubgraphs, FederationError> {
+    extract_subgraphs(supergraph_schema, validate_extracted_subgraphs, None)
+}
+
+/// Like `extract_subgraphs_from_supergraph()`, but only returns the subgraph named `subgraph_name`.
+/// The other subgraphs are neither completed with the federation operations nor validated, which
+/// is where most of the extraction time goes.
+pub(crate) fn extract_subgraph_from_supergraph(
+    supergraph_schema: &FederationSchema,
+    subgraph_name: &str,
+    validate_extracted_subgraph: Option<bool>,
+) -> Result<ValidFederationSubgraph, FederationError> {
+    let ValidFederationSubgraphs { mut subgraphs } = extract_subgraphs(
+        supergraph_schema,
+        validate_extracted_subgraph,
+        Some(subgraph_name),
+    )?;
+    subgraphs.remove(subgraph_name).ok_or_else(|| {
+        FederationError::internal(format!(
+            "Subgraph \"{}\" unexpectedly missing from the extracted subgraphs",
+            subgraph_name
+        ))
+    })
+}
+
+fn extract_subgraphs(
+    supergraph_schema: &FederationSchema,
+    validate_extracted_subgraphs: Option<bool>,
+    only_subgraph_name: Option<&str>,
 ) -> Result<ValidFederationSubgraphs, FederationError> {
     let validate_extracted_subgraphs = validate_extracted_subgraphs.unwrap_or(true);
     let (link_spec_definition, join_spec_definition) =
@@ -97,6 +136,22 @@ pub(crate) fn extract_subgraphs_from_supergraph(
     let is_fed_1 = *join_spec_definition.version() == Version { major: 0, minor: 1 };
     let (mut subgraphs, federation_spec_definitions, graph_enum_value_name_to_subgraph_name) =
         collect_empty_subgraphs(supergraph_schema, join_spec_definition)?;
+    if let Some(subgraph_name) = only_subgraph_name {
+        if !graph_enum_value_name_to_subgraph_name
+            .values()
+            .any(|name| name.as_ref() == subgraph_name)
+        {
+            return Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "No subgraph named \"{}\" is defined in the join__Graph enum of the supergraph",
+                    subgraph_name
+                ),
+            }
+            .into());
+        }
+    }
+    let is_extracted =
+        |subgraph_name: &str| only_subgraph_name.map_or(true, |name| name == subgraph_name);
 
     let filtered_types: Vec<_> = supergraph_schema
         .get_types()
@@ -128,7 +183,10 @@ pub(crate) fn extract_subgraphs_from_supergraph(
         )?;
     }
 
-    for graph_enum_value in graph_enum_value_name_to_subgraph_name.keys() {
+    for (graph_enum_value, subgraph_name) in &graph_enum_value_name_to_subgraph_name {
+        if !is_extracted(subgraph_name) {
+            continue;
+        }
         let subgraph = get_subgraph(
             &mut subgraphs,
             &graph_enum_value_name_to_subgraph_name,
@@ -144,6 +202,9 @@ pub(crate) fn extract_subgraphs_from_supergraph(
 
     let mut valid_subgraphs = ValidFederationSubgraphs::new();
     for (_, mut subgraph) in subgraphs {
+        if !is_extracted(&subgraph.name) {
+            continue;
+        }
         let valid_subgraph_schema = if validate_extracted_subgraphs {
             match subgraph.schema.validate_or_return_self() {
                 Ok(schema) => schema,
@@ -322,10 +383,25 @@ fn extract_subgraphs_from_fed_2_supergraph(
         &original_directive_names,
     )?;
 
+    extract_context_directives(
+        supergraph_schema,
+        subgraphs,
+        graph_enum_value_name_to_subgraph_name,
+        federation_spec_definitions,
+    )?;
+
+    extract_inaccessible_directives(
+        supergraph_schema,
+        subgraphs,
+        graph_enum_value_name_to_subgraph_name,
+        federation_spec_definitions,
+    )?;
+
     extract_join_directives(
         supergraph_schema,
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
+        federation_spec_definitions,
     )?;
 
     // We add all the "executable" directive definitions from the supergraph to each subgraphs, as
@@ -772,6 +848,11 @@ fn extract_object_type_content(
 
         for (field_name, field) in type_.fields.iter() {
             let field_pos = pos.field(field_name.clone());
+            CostSpecDefinition::validate_list_size(
+                supergraph_schema,
+                &field_pos.clone().into(),
+                original_directive_names,
+            )?;
             let mut field_directive_applications = Vec::new();
             for directive in field.directives.get_all(&field_directive_definition.name) {
                 field_directive_applications
@@ -974,6 +1055,15 @@ fn extract_interface_type_content(
         }
 
         for (field_name, field) in type_.fields.iter() {
+            CostSpecDefinition::validate_list_size(
+                supergraph_schema,
+                &InterfaceFieldDefinitionPosition {
+                    type_name: type_name.clone(),
+                    field_name: field_name.clone(),
+                }
+                .into(),
+                original_directive_names,
+            )?;
             let mut field_directive_applications = Vec::new();
             for directive in field.directives.get_all(&field_directive_definition.name) {
                 field_directive_applications
@@ -1166,6 +1256,10 @@ fn extract_enum_type_content(
     // This was added in join 0.3, so it can genuinely be None.
     let enum_value_directive_definition =
         join_spec_definition.enum_value_directive_definition(supergraph_schema)?;
+    let tag_directive_name = supergraph_schema
+        .metadata()
+        .and_then(|metadata| metadata.for_identity(&Identity::tag_identity()))
+        .map(|link| link.directive_name_in_schema(&FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC));
 
     for TypeInfo {
         name: type_name,
@@ -1202,6 +1296,14 @@ fn extract_enum_type_content(
 
         for (value_name, value) in type_.values.iter() {
             let value_pos = pos.value(value_name.clone());
+            let tags = match &tag_directive_name {
+                Some(tag_directive_name) => value
+                    .directives
+                    .get_all(tag_directive_name)
+                    .map(|directive| directive_required_string_argument(directive, &name!("name")))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
             let mut enum_value_directive_applications = Vec::new();
             if let Some(enum_value_directive_definition) = enum_value_directive_definition {
                 for directive in value
@@ -1219,14 +1321,13 @@ fn extract_enum_type_content(
                         graph_enum_value_name_to_subgraph_name,
                         graph_enum_value,
                     )?;
-                    value_pos.insert(
-                        &mut subgraph.schema,
-                        Component::new(EnumValueDefinition {
-                            description: None,
-                            value: value_name.clone(),
-                            directives: Default::default(),
-                        }),
+                    let enum_value = enum_value_in_subgraph(
+                        &subgraph.schema,
+                        federation_spec_definitions.get(graph_enum_value),
+                        value_name,
+                        &tags,
                     )?;
+                    value_pos.insert(&mut subgraph.schema, Component::new(enum_value))?;
                 }
             } else {
                 for enum_value_directive_application in &enum_value_directive_applications {
@@ -1247,14 +1348,13 @@ fn extract_enum_type_content(
                             }.into()
                         );
                     }
-                    value_pos.insert(
-                        &mut subgraph.schema,
-                        Component::new(EnumValueDefinition {
-                            description: None,
-                            value: value_name.clone(),
-                            directives: Default::default(),
-                        }),
+                    let enum_value = enum_value_in_subgraph(
+                        &subgraph.schema,
+                        federation_spec_definitions.get(&enum_value_directive_application.graph),
+                        value_name,
+                        &tags,
                     )?;
+                    value_pos.insert(&mut subgraph.schema, Component::new(enum_value))?;
                 }
             }
         }
@@ -1263,6 +1363,35 @@ fn extract_enum_type_content(
     Ok(())
 }
 
+/// Builds the definition of an enum value in a subgraph, carrying the `@tag`s the value has in
+/// the supergraph.
+fn enum_value_in_subgraph(
+    subgraph_schema: &FederationSchema,
+    federation_spec_definition: Option<&&'static FederationSpecDefinition>,
+    value_name: &Name,
+    tags: &[&str],
+) -> Result<EnumValueDefinition, FederationError> {
+    let mut enum_value = EnumValueDefinition {
+        description: None,
+        value: value_name.clone(),
+        directives: Default::default(),
+    };
+    if tags.is_empty() {
+        return Ok(enum_value);
+    }
+    let federation_spec_definition = federation_spec_definition.ok_or_else(|| {
+        SingleFederationError::InvalidFederationSupergraph {
+            message: "Subgraph unexpectedly does not use federation spec".to_owned(),
+        }
+    })?;
+    for tag in tags {
+        enum_value.directives.push(Node::new(
+            federation_spec_definition.tag_directive(subgraph_schema, tag.to_string())?,
+        ));
+    }
+    Ok(enum_value)
+}
+
 fn extract_input_object_type_content(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
@@ -1389,6 +1518,7 @@ fn add_subgraph_field(
             override_: None,
             override_label: None,
             user_overridden: None,
+            context_arguments: None,
         });
     let subgraph_field_type = match &field_directive_application.type_ {
         Some(t) => decode_type(t)?,
@@ -1417,6 +1547,10 @@ fn add_subgraph_field(
                 &subgraph.schema,
                 &argument.directives,
                 &mut destination_argument.directives,
+                format!(
+                    "{}({}:)",
+                    object_or_interface_field_definition_position, argument.name
+                ),
                 original_directive_names,
             )?;
         }
@@ -1425,6 +1559,29 @@ fn add_subgraph_field(
             .arguments
             .push(Node::new(destination_argument))
     }
+    for context_argument in field_directive_application
+        .context_arguments
+        .iter()
+        .flatten()
+    {
+        let (_, context_name) = split_context_name(context_argument.context)?;
+        let mut destination_argument = InputValueDefinition {
+            description: None,
+            name: Name::new(context_argument.name)?,
+            ty: Node::new(decode_type(context_argument.type_)?),
+            default_value: None,
+            directives: Default::default(),
+        };
+        destination_argument.directives.push(Node::new(
+            federation_spec_definition.from_context_directive(
+                &subgraph.schema,
+                format!("${} {}", context_name, context_argument.selection),
+            )?,
+        ));
+        subgraph_field
+            .arguments
+            .push(Node::new(destination_argument));
+    }
     if let Some(requires) = &field_directive_application.requires {
         subgraph_field.directives.push(Node::new(
             federation_spec_definition
@@ -1470,6 +1627,7 @@ fn add_subgraph_field(
             &subgraph.schema,
             &field.directives,
             &mut subgraph_field.directives,
+            &object_or_interface_field_definition_position,
             original_directive_names,
         )?;
     }
@@ -1504,6 +1662,7 @@ fn add_subgraph_input_field(
             override_: None,
             override_label: None,
             user_overridden: None,
+            context_arguments: None,
         });
     let subgraph_input_field_type = match &field_directive_application.type_ {
         Some(t) => Node::new(decode_type(t)?),
@@ -1522,6 +1681,7 @@ fn add_subgraph_input_field(
             &subgraph.schema,
             &input_field.directives,
             &mut subgraph_input_field.directives,
+            &input_object_field_definition_position,
             original_directive_names,
         )?;
     }
@@ -2118,6 +2278,295 @@ fn maybe_dump_subgraph_schema(subgraph: FederationSubgraph, message: &mut String
     };
 }
 
+////////////////////////////////////////////////////////////////////////////////
+/// @context extraction
+
+/// Splits the name of a context in the supergraph, which is prefixed by the name of the subgraph
+/// that defines it (e.g. `Subgraph1__ctx`), into the subgraph name and the context name.
+fn split_context_name(name: &str) -> Result<(&str, &str), FederationError> {
+    name.rsplit_once("__")
+        .filter(|(subgraph_name, context_name)| {
+            !subgraph_name.is_empty() && !context_name.is_empty()
+        })
+        .ok_or_else(|| {
+            SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid context \"{}\": expected a name of the form \"<subgraph>__<context>\"",
+                    name
+                ),
+            }
+            .into()
+        })
+}
+
+/// Returns the graph enum value and context name of each `@context` application in `directives`.
+fn context_directive_applications<'doc>(
+    directives: &'doc DirectiveList,
+    context_directive_name: &Name,
+    graph_enum_value_name_to_subgraph_name: &IndexMap<Name, Arc<str>>,
+) -> Result<Vec<(Name, &'doc str)>, FederationError> {
+    directives
+        .get_all(context_directive_name)
+        .map(|directive| {
+            let name = directive_required_string_argument(directive, &name!("name"))?;
+            let (subgraph_name, context_name) = split_context_name(name)?;
+            let graph_enum_value = graph_enum_value_name_to_subgraph_name
+                .iter()
+                .find_map(|(graph_enum_value, name)| {
+                    (name.as_ref() == subgraph_name).then(|| graph_enum_value.clone())
+                })
+                .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                    message: format!(
+                        "Invalid context \"{}\": no subgraph is named \"{}\"",
+                        name, subgraph_name
+                    ),
+                })?;
+            Ok((graph_enum_value, context_name))
+        })
+        .collect()
+}
+
+fn context_directive_in_subgraph(
+    subgraph_schema: &FederationSchema,
+    federation_spec_definition: Option<&&'static FederationSpecDefinition>,
+    context_name: &str,
+) -> Result<Directive, FederationError> {
+    let federation_spec_definition =
+        federation_spec_definition.ok_or_else(|| SingleFederationError::Internal {
+            message: "Subgraph unexpectedly does not use federation spec".to_owned(),
+        })?;
+    federation_spec_definition.context_directive(subgraph_schema, context_name.to_owned())
+}
+
+/// Converts `@context(name: "A__ctx")` on a type of the supergraph to `@context(name: "ctx")` on
+/// that type in the A subgraph.
+fn extract_context_directives(
+    supergraph_schema: &FederationSchema,
+    subgraphs: &mut FederationSubgraphs,
+    graph_enum_value_name_to_subgraph_name: &IndexMap<Name, Arc<str>>,
+    federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
+) -> Result<(), FederationError> {
+    let Some(context_link) = supergraph_schema
+        .metadata()
+        .and_then(|metadata| metadata.for_identity(&Identity::context_identity()))
+    else {
+        return Ok(());
+    };
+    let context_directive_name = context_link.directive_name_in_schema(&name!("context"));
+    let Ok(context_directives) = supergraph_schema
+        .referencers()
+        .get_directive(&context_directive_name)
+    else {
+        return Ok(());
+    };
+
+    for object_type_pos in &context_directives.object_types {
+        let object_type = object_type_pos.get(supergraph_schema.schema())?;
+        for (graph_enum_value, context_name) in context_directive_applications(
+            &object_type.directives,
+            &context_directive_name,
+            graph_enum_value_name_to_subgraph_name,
+        )? {
+            let subgraph = get_subgraph(
+                subgraphs,
+                graph_enum_value_name_to_subgraph_name,
+                &graph_enum_value,
+            )?;
+            let directive = context_directive_in_subgraph(
+                &subgraph.schema,
+                federation_spec_definitions.get(&graph_enum_value),
+                context_name,
+            )?;
+            object_type_pos.insert_directive(&mut subgraph.schema, Component::new(directive))?;
+        }
+    }
+
+    for interface_type_pos in &context_directives.interface_types {
+        let interface_type = interface_type_pos.get(supergraph_schema.schema())?;
+        for (graph_enum_value, context_name) in context_directive_applications(
+            &interface_type.directives,
+            &context_directive_name,
+            graph_enum_value_name_to_subgraph_name,
+        )? {
+            let subgraph = get_subgraph(
+                subgraphs,
+                graph_enum_value_name_to_subgraph_name,
+                &graph_enum_value,
+            )?;
+            let directive = context_directive_in_subgraph(
+                &subgraph.schema,
+                federation_spec_definitions.get(&graph_enum_value),
+                context_name,
+            )?;
+            interface_type_pos.insert_directive(&mut subgraph.schema, Component::new(directive))?;
+        }
+    }
+
+    for union_type_pos in &context_directives.union_types {
+        let union_type = union_type_pos.get(supergraph_schema.schema())?;
+        for (graph_enum_value, context_name) in context_directive_applications(
+            &union_type.directives,
+            &context_directive_name,
+            graph_enum_value_name_to_subgraph_name,
+        )? {
+            let subgraph = get_subgraph(
+                subgraphs,
+                graph_enum_value_name_to_subgraph_name,
+                &graph_enum_value,
+            )?;
+            let directive = context_directive_in_subgraph(
+                &subgraph.schema,
+                federation_spec_definitions.get(&graph_enum_value),
+                context_name,
+            )?;
+            union_type_pos.insert_directive(&mut subgraph.schema, Component::new(directive))?;
+        }
+    }
+
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// @inaccessible extraction
+
+/// Adds `@inaccessible` to the elements of each subgraph that are `@inaccessible` in the
+/// supergraph. Ownership comes from `@join__type`/`@join__field`: the directive is only added in
+/// the subgraphs that define the element. The supergraph doesn't record which of those subgraphs
+/// marked the element as inaccessible, so an element defined in several subgraphs gets it in all
+/// of them. Only `@join__directive(graphs: [...], name: "inaccessible")` places the directive in
+/// some of them: elements carrying it are left to [`extract_join_directives`].
+fn extract_inaccessible_directives(
+    supergraph_schema: &FederationSchema,
+    subgraphs: &mut FederationSubgraphs,
+    graph_enum_value_name_to_subgraph_name: &IndexMap<Name, Arc<str>>,
+    federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
+) -> Result<(), FederationError> {
+    let Some(inaccessible_link) = supergraph_schema
+        .metadata()
+        .and_then(|metadata| metadata.for_identity(&Identity::inaccessible_identity()))
+    else {
+        return Ok(());
+    };
+    let inaccessible_directive_name =
+        inaccessible_link.directive_name_in_schema(&FEDERATION_INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC);
+    let Ok(inaccessible_directives) = supergraph_schema
+        .referencers()
+        .get_directive(&inaccessible_directive_name)
+    else {
+        return Ok(());
+    };
+
+    for graph_enum_value in graph_enum_value_name_to_subgraph_name.keys() {
+        let subgraph = get_subgraph(
+            subgraphs,
+            graph_enum_value_name_to_subgraph_name,
+            graph_enum_value,
+        )?;
+        let federation_spec_definition = federation_spec_definitions
+            .get(graph_enum_value)
+            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                message: "Subgraph unexpectedly does not use federation spec".to_owned(),
+            })?;
+        let directive = federation_spec_definition.inaccessible_directive(&subgraph.schema)?;
+        let schema = &mut subgraph.schema;
+
+        for pos in &inaccessible_directives.scalar_types {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.object_types {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.object_fields {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.object_field_arguments {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.interface_types {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.interface_fields {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.interface_field_arguments {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.union_types {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.enum_types {
+            let placed_by_join_directive = pos
+                .try_get(supergraph_schema.schema())
+                .is_some_and(|element| has_inaccessible_join_directive(element.directives.iter()));
+            if !placed_by_join_directive && pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.enum_values {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.input_object_types {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Component::new(directive.clone()))?;
+            }
+        }
+        for pos in &inaccessible_directives.input_object_fields {
+            if pos.try_get(schema.schema()).is_some() {
+                pos.insert_directive(schema, Node::new(directive.clone()))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the element has a `@join__directive(name: "inaccessible")`.
+fn has_inaccessible_join_directive<'a, D>(mut directives: impl Iterator<Item = &'a D>) -> bool
+where
+    D: Deref<Target = Directive> + 'a,
+{
+    directives.any(|directive| {
+        directive.name == JOIN_DIRECTIVE
+            && directive
+                .specified_argument_by_name("name")
+                .and_then(|name| name.as_str())
+                == Some(FEDERATION_INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC.as_str())
+    })
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// @join__directive extraction
 
@@ -2130,6 +2579,7 @@ fn extract_join_directives(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, Arc<str>>,
+    federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
 ) -> Result<(), FederationError> {
     let join_directives = match supergraph_schema
         .referencers()
@@ -2217,29 +2667,246 @@ fn extract_join_directives(
                     &subgraph_enum_value,
                 )?;
 
-                object_field_pos
-                    .insert_directive(&mut subgraph.schema, Node::new(directive.clone()))?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    object_field_pos,
+                )?;
+
+                object_field_pos.insert_directive(&mut subgraph.schema, Node::new(directive))?;
+            }
+        }
+    }
+
+    for object_type_pos in &join_directives.object_types {
+        let object_type = object_type_pos.get(supergraph_schema.schema())?;
+        let directives = object_type
+            .directives
+            .iter()
+            .filter_map(|d| {
+                if d.name == JOIN_DIRECTIVE {
+                    Some(join_directive_to_real_directive(d))
+                } else {
+                    None
+                }
+            })
+            .collect_vec();
+
+        for (directive, subgraph_enum_values) in directives {
+            for subgraph_enum_value in subgraph_enum_values {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &subgraph_enum_value,
+                )?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    object_type_pos,
+                )?;
+
+                object_type_pos
+                    .insert_directive(&mut subgraph.schema, Component::new(directive))?;
+            }
+        }
+    }
+
+    for interface_field_pos in &join_directives.interface_fields {
+        let interface_field = interface_field_pos.get(supergraph_schema.schema())?;
+        let directives = interface_field
+            .directives
+            .iter()
+            .filter_map(|d| {
+                if d.name == JOIN_DIRECTIVE {
+                    Some(join_directive_to_real_directive(d))
+                } else {
+                    None
+                }
+            })
+            .collect_vec();
+
+        for (directive, subgraph_enum_values) in directives {
+            for subgraph_enum_value in subgraph_enum_values {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &subgraph_enum_value,
+                )?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    interface_field_pos,
+                )?;
+
+                interface_field_pos.insert_directive(&mut subgraph.schema, Node::new(directive))?;
+            }
+        }
+    }
+
+    for interface_type_pos in &join_directives.interface_types {
+        let interface_type = interface_type_pos.get(supergraph_schema.schema())?;
+        let directives = interface_type
+            .directives
+            .iter()
+            .filter_map(|d| {
+                if d.name == JOIN_DIRECTIVE {
+                    Some(join_directive_to_real_directive(d))
+                } else {
+                    None
+                }
+            })
+            .collect_vec();
+
+        for (directive, subgraph_enum_values) in directives {
+            for subgraph_enum_value in subgraph_enum_values {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &subgraph_enum_value,
+                )?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    interface_type_pos,
+                )?;
+
+                interface_type_pos
+                    .insert_directive(&mut subgraph.schema, Component::new(directive))?;
+            }
+        }
+    }
+
+    for scalar_type_pos in &join_directives.scalar_types {
+        let scalar_type = scalar_type_pos.get(supergraph_schema.schema())?;
+        let directives = scalar_type
+            .directives
+            .iter()
+            .filter_map(|d| {
+                if d.name == JOIN_DIRECTIVE {
+                    Some(join_directive_to_real_directive(d))
+                } else {
+                    None
+                }
+            })
+            .collect_vec();
+
+        for (directive, subgraph_enum_values) in directives {
+            for subgraph_enum_value in subgraph_enum_values {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &subgraph_enum_value,
+                )?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    scalar_type_pos,
+                )?;
+
+                scalar_type_pos
+                    .insert_directive(&mut subgraph.schema, Component::new(directive))?;
+            }
+        }
+    }
+
+    for enum_type_pos in &join_directives.enum_types {
+        let enum_type = enum_type_pos.get(supergraph_schema.schema())?;
+        let directives = enum_type
+            .directives
+            .iter()
+            .filter_map(|d| {
+                if d.name == JOIN_DIRECTIVE {
+                    Some(join_directive_to_real_directive(d))
+                } else {
+                    None
+                }
+            })
+            .collect_vec();
+
+        for (directive, subgraph_enum_values) in directives {
+            for subgraph_enum_value in subgraph_enum_values {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &subgraph_enum_value,
+                )?;
+                let directive = join_directive_in_subgraph(
+                    directive.clone(),
+                    &subgraph.schema,
+                    federation_spec_definitions.get(&subgraph_enum_value),
+                    enum_type_pos,
+                )?;
+
+                enum_type_pos.insert_directive(&mut subgraph.schema, Component::new(directive))?;
             }
         }
     }
 
     // TODO
     // - join_directives.directive_arguments
-    // - join_directives.enum_types
     // - join_directives.enum_values
     // - join_directives.input_object_fields
     // - join_directives.input_object_types
     // - join_directives.interface_field_arguments
-    // - join_directives.interface_fields
-    // - join_directives.interface_types
     // - join_directives.object_field_arguments
-    // - join_directives.object_types
-    // - join_directives.scalar_types
     // - join_directives.union_types
 
     Ok(())
 }
 
+/// The directives of the federation spec that may be carried by `@join__directive`.
+const JOIN_DIRECTIVE_FEDERATION_DIRECTIVE_NAMES_IN_SPEC: [Name; 4] = [
+    FEDERATION_AUTHENTICATED_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_REQUIRES_SCOPES_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_POLICY_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC,
+];
+
+/// `@join__directive` refers to the federation and cost directives by their name in the spec,
+/// which the subgraph may import under another name (e.g. `@federation__cost`). The coordinate of
+/// the element that the directive is applied to is used to report invalid `@cost` weights.
+fn join_directive_in_subgraph(
+    directive: Directive,
+    subgraph_schema: &FederationSchema,
+    federation_spec_definition: Option<&&'static FederationSpecDefinition>,
+    coordinate: impl Display,
+) -> Result<Directive, FederationError> {
+    if directive.name == COST_DIRECTIVE_NAME_IN_SPEC {
+        CostSpecDefinition::validate_cost_weight(&directive, coordinate)?;
+    }
+    let Some(federation_spec_definition) = federation_spec_definition else {
+        return Ok(directive);
+    };
+    if JOIN_DIRECTIVE_FEDERATION_DIRECTIVE_NAMES_IN_SPEC.contains(&directive.name) {
+        let Some(name_in_schema) = federation_spec_definition
+            .directive_name_in_schema(subgraph_schema, &directive.name)?
+        else {
+            return Ok(directive);
+        };
+        return Ok(Directive {
+            name: name_in_schema,
+            arguments: directive.arguments,
+        });
+    }
+    let Some(cost_spec_definition) =
+        federation_spec_definition.get_cost_spec_definition(subgraph_schema)
+    else {
+        return Ok(directive);
+    };
+    if directive.name == COST_DIRECTIVE_NAME_IN_SPEC {
+        cost_spec_definition.cost_directive(subgraph_schema, directive.arguments)
+    } else if directive.name == LIST_SIZE_DIRECTIVE_NAME_IN_SPEC {
+        cost_spec_definition.list_size_directive(subgraph_schema, directive.arguments)
+    } else {
+        Ok(directive)
+    }
+}
+
 fn join_directive_to_real_directive(directive: &Node<Directive>) -> (Directive, Vec<Name>) {
     let subgraph_enum_values = directive
         .specified_argument_by_name("graphs")
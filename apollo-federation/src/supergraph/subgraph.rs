@@ -58,6 +58,8 @@ pub struct ValidFederationSubgraph {
     pub schema: ValidFederationSchema,
 }
 
+/// The subgraphs extracted from a supergraph, ordered by subgraph name. Iterating over them always
+/// yields the subgraphs in that order, regardless of how the `join__Graph` enum is declared.
 pub struct ValidFederationSubgraphs {
     pub(super) subgraphs: BTreeMap<Arc<str>, ValidFederationSubgraph>,
 }
@@ -93,6 +95,7 @@ impl ValidFederationSubgraphs {
     }
 }
 
+/// Yields `(name, subgraph)` pairs, ordered by subgraph name.
 impl IntoIterator for ValidFederationSubgraphs {
     type Item = <BTreeMap<Arc<str>, ValidFederationSubgraph> as IntoIterator>::Item;
     type IntoIter = <BTreeMap<Arc<str>, ValidFederationSubgraph> as IntoIterator>::IntoIter;
@@ -1,4 +1,5 @@
 use apollo_compiler::coord;
+use apollo_compiler::schema::ExtendedType;
 use apollo_compiler::schema::Value;
 use apollo_compiler::Node;
 use apollo_federation::Supergraph;
@@ -173,6 +174,106 @@ fn preserve_default_values_of_input_fields() {
     );
 }
 
+#[test]
+fn preserve_default_values_of_arguments() {
+    let supergraph = Supergraph::new(r#"
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/join/v0.2", for: EXECUTION)
+    {
+      query: Query
+    }
+
+    directive @join__field(graph: join__Graph!, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+    directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+    directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+    directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+    directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+    directive @limit(max: Int! = 100) on FIELD
+
+    interface Paginated
+      @join__type(graph: SERVICE)
+    {
+      items(first: Int! = 10): [String]
+    }
+
+    scalar join__FieldSet
+
+    enum join__Graph {
+      SERVICE @join__graph(name: "service", url: "")
+    }
+
+    scalar link__Import
+
+    enum link__Purpose {
+      """
+      \`SECURITY\` features provide metadata necessary to securely resolve fields.
+      """
+      SECURITY
+
+      """
+      \`EXECUTION\` features provide metadata necessary for operation execution.
+      """
+      EXECUTION
+    }
+
+    type Query implements Paginated
+      @join__implements(graph: SERVICE, interface: "Paginated")
+      @join__type(graph: SERVICE)
+    {
+      items(first: Int! = 10): [String]
+      search(term: String = "", exact: Boolean! = false): [String]
+    }
+    "#).expect("should parse");
+
+    let subgraphs = supergraph
+        .extract_subgraphs()
+        .expect("should extract subgraphs");
+
+    let service = subgraphs
+        .get("service")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    assert_eq!(
+        coord!(Query.items(first:))
+            .lookup(service)
+            .unwrap()
+            .default_value,
+        Some(Node::new(Value::Int(10.into())))
+    );
+    assert_eq!(
+        coord!(Paginated.items(first:))
+            .lookup(service)
+            .unwrap()
+            .default_value,
+        Some(Node::new(Value::Int(10.into())))
+    );
+    assert_eq!(
+        coord!(Query.search(term:))
+            .lookup(service)
+            .unwrap()
+            .default_value,
+        Some(Node::new(Value::String("".to_owned())))
+    );
+    assert_eq!(
+        coord!(Query.search(exact:))
+            .lookup(service)
+            .unwrap()
+            .default_value,
+        Some(Node::new(Value::Boolean(false)))
+    );
+    assert_eq!(
+        coord!(@limit(max:)).lookup(service).unwrap().default_value,
+        Some(Node::new(Value::Int(100.into())))
+    );
+}
+
 #[test]
 fn erase_empty_types_due_to_overridden_fields() {
     let supergraph = Supergraph::new(r#"
@@ -384,6 +485,1331 @@ fn extracts_demand_control_directives() {
     insta::assert_snapshot!(snapshot);
 }
 
+#[test]
+fn does_not_extract_list_size_with_unknown_sized_fields() {
+    let error = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/cost/v0.1", import: ["@listSize"])
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        directive @listSize(assumedSize: Int, slicingArguments: [String!], sizedFields: [String!], requireOneSlicingArgument: Boolean = true) on FIELD_DEFINITION
+
+        type HasInts
+          @join__type(graph: SUBGRAPHWITHLISTSIZE)
+        {
+          ints: [Int!]
+        }
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          SUBGRAPHWITHLISTSIZE @join__graph(name: "subgraphWithListSize", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          """
+          `SECURITY` features provide metadata necessary to securely resolve fields.
+          """
+          SECURITY
+
+          """
+          `EXECUTION` features provide metadata necessary for operation execution.
+          """
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: SUBGRAPHWITHLISTSIZE)
+        {
+          fieldWithDynamicListSize(first: Int!): HasInts @join__field(graph: SUBGRAPHWITHLISTSIZE) @listSize(slicingArguments: ["first"], sizedFields: ["bogus"], requireOneSlicingArgument: true)
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect_err("sized field does not exist");
+
+    assert!(
+        error
+            .to_string()
+            .contains(r#"Sized field "bogus" of "@listSize" on "Query.fieldWithDynamicListSize" is not a field of "HasInts""#),
+        "{error}"
+    );
+}
+
+#[test]
+fn extracts_list_size_on_interface_fields() {
+    let supergraph = r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/cost/v0.1", import: ["@listSize"])
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        directive @listSize(assumedSize: Int, slicingArguments: [String!], sizedFields: [String!], requireOneSlicingArgument: Boolean = true) on FIELD_DEFINITION
+
+        interface HasPages
+          @join__type(graph: A)
+        {
+          page(first: Int): IntPage @listSize(slicingArguments: ["first"], sizedFields: ["items"])
+        }
+
+        type IntPage
+          @join__type(graph: A)
+        {
+          items: [Int!]
+        }
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+        {
+          pages: HasPages
+        }
+
+        type Thing implements HasPages
+          @join__implements(graph: A, interface: "HasPages")
+          @join__type(graph: A)
+        {
+          page(first: Int): IntPage
+        }
+    "#;
+
+    let subgraphs = Supergraph::new(supergraph)
+        .expect("is supergraph")
+        .extract_subgraphs()
+        .expect("extracts subgraphs");
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let page = coord!(HasPages.page).lookup_field(a).unwrap();
+    assert_eq!(
+        page.directives
+            .get("federation__listSize")
+            .expect("has @listSize")
+            .to_string(),
+        r#"@federation__listSize(slicingArguments: ["first"], sizedFields: ["items"])"#
+    );
+
+    let error = Supergraph::new(&supergraph.replace(r#"["first"]"#, r#"["last"]"#))
+        .expect("is supergraph")
+        .extract_subgraphs()
+        .expect_err("slicing argument does not exist");
+    assert!(
+        error
+            .to_string()
+            .contains(r#"Slicing argument "last" of "@listSize" on "HasPages.page" is not an argument of the field"#),
+        "{error}"
+    );
+
+    let error = Supergraph::new(&supergraph.replace(
+        r#"@listSize(slicingArguments: ["first"]"#,
+        r#"@listSize(assumedSize: -1, slicingArguments: ["first"]"#,
+    ))
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect_err("assumed size is negative");
+    assert!(
+        error.to_string().contains(
+            r#"Argument "assumedSize" of "@listSize" on "HasPages.page" must be non-negative, got -1"#
+        ),
+        "{error}"
+    );
+
+    let error = Supergraph::new(&supergraph.replace(
+        r#"@listSize(slicingArguments: ["first"]"#,
+        r#"@listSize(slicingArguments: []"#,
+    ))
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect_err("no slicing argument to require");
+    assert!(
+        error.to_string().contains(
+            r#""@listSize" on "HasPages.page" requires one slicing argument, but "slicingArguments" is empty"#
+        ),
+        "{error}"
+    );
+
+    // Nothing to require when slicing arguments are optional
+    Supergraph::new(&supergraph.replace(
+        r#"@listSize(slicingArguments: ["first"]"#,
+        r#"@listSize(slicingArguments: [], requireOneSlicingArgument: false"#,
+    ))
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+}
+
+#[test]
+fn does_not_extract_negative_cost_weights() {
+    let error = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/cost/v0.1", import: ["@cost"])
+        {
+          query: Query
+        }
+
+        directive @cost(weight: Int!) on ARGUMENT_DEFINITION | ENUM | FIELD_DEFINITION | INPUT_FIELD_DEFINITION | OBJECT | SCALAR
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          SUBGRAPHWITHCOST @join__graph(name: "subgraphWithCost", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: SUBGRAPHWITHCOST)
+        {
+          argWithCost(arg: Int @cost(weight: -5)): Int @join__field(graph: SUBGRAPHWITHCOST)
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect_err("cost weight is negative");
+
+    assert!(
+        error
+            .to_string()
+            .contains(r#"Invalid "@cost" on "Query.argWithCost(arg:)": Argument "weight" of directive "@cost" must not be negative, got -5."#),
+        "{error}"
+    );
+}
+
+#[test]
+fn extracts_join_directive_cost_on_scalars_and_enums() {
+    let supergraph = r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION | SCALAR | ENUM
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        enum AorB
+          @join__type(graph: A)
+          @join__type(graph: B)
+          @join__directive(graphs: [B], name: "cost", args: {weight: 15})
+        {
+          A @join__enumValue(graph: A) @join__enumValue(graph: B)
+          B @join__enumValue(graph: A) @join__enumValue(graph: B)
+        }
+
+        scalar ExpensiveInt
+          @join__type(graph: A)
+          @join__type(graph: B)
+          @join__directive(graphs: [A], name: "cost", args: {weight: 30})
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          a: ExpensiveInt @join__field(graph: A)
+          b: AorB @join__field(graph: B)
+          c: ExpensiveInt @join__field(graph: B)
+          d: AorB @join__field(graph: A)
+        }
+    "#;
+
+    let subgraphs = Supergraph::new(supergraph)
+        .expect("is supergraph")
+        .extract_subgraphs()
+        .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let cost_weight = |schema: &apollo_compiler::Schema, type_name: &str| {
+        schema.types[type_name]
+            .directives()
+            .get("federation__cost")
+            .and_then(|cost| cost.specified_argument_by_name("weight"))
+            .cloned()
+    };
+
+    assert_eq!(
+        cost_weight(a, "ExpensiveInt"),
+        Some(Node::new(Value::Int(30.into())))
+    );
+    assert_eq!(cost_weight(b, "ExpensiveInt"), None);
+    assert_eq!(cost_weight(a, "AorB"), None);
+    assert_eq!(
+        cost_weight(b, "AorB"),
+        Some(Node::new(Value::Int(15.into())))
+    );
+
+    let error = Supergraph::new(&supergraph.replace("{weight: 30}", "{weight: -30}"))
+        .expect("is supergraph")
+        .extract_subgraphs()
+        .expect_err("cost weight is negative");
+    assert!(
+        error
+            .to_string()
+            .contains(r#"Invalid "@cost" on "ExpensiveInt": Argument "weight" of directive "@cost" must not be negative, got -30."#),
+        "{error}"
+    );
+}
+
+#[test]
+fn extracts_join_directive_authenticated() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION | SCALAR | ENUM
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        interface Node
+          @join__type(graph: A)
+          @join__type(graph: B)
+          @join__directive(graphs: [A], name: "authenticated")
+        {
+          id: ID! @join__directive(graphs: [B], name: "authenticated")
+        }
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          me: User @join__field(graph: A) @join__directive(graphs: [A], name: "authenticated")
+          node: Node @join__field(graph: B)
+        }
+
+        type User implements Node
+          @join__type(graph: A, key: "id")
+          @join__type(graph: B, key: "id")
+          @join__implements(graph: A, interface: "Node")
+          @join__implements(graph: B, interface: "Node")
+          @join__directive(graphs: [B], name: "authenticated")
+        {
+          id: ID!
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let type_is_authenticated = |schema: &apollo_compiler::Schema, type_name: &str| {
+        schema.types[type_name]
+            .directives()
+            .has("federation__authenticated")
+    };
+    let field_is_authenticated =
+        |schema: &apollo_compiler::Schema, type_name: &str, field_name: &str| {
+            schema
+                .type_field(type_name, field_name)
+                .expect("missing field")
+                .directives
+                .has("federation__authenticated")
+        };
+
+    assert!(field_is_authenticated(a, "Query", "me"));
+    assert!(type_is_authenticated(a, "Node"));
+    assert!(!type_is_authenticated(b, "Node"));
+    assert!(!field_is_authenticated(a, "Node", "id"));
+    assert!(field_is_authenticated(b, "Node", "id"));
+    assert!(!type_is_authenticated(a, "User"));
+    assert!(type_is_authenticated(b, "User"));
+}
+
+#[test]
+fn extracts_join_directive_requires_scopes() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION | SCALAR | ENUM
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          user: User @join__field(graph: A)
+          users: [User] @join__field(graph: B) @join__directive(graphs: [B], name: "requiresScopes", args: {scopes: []})
+        }
+
+        type User
+          @join__type(graph: A, key: "id")
+          @join__type(graph: B, key: "id")
+          @join__directive(graphs: [A], name: "requiresScopes", args: {scopes: [["read:user"], ["admin", "read:all"]]})
+          @join__directive(graphs: [B], name: "requiresScopes", args: {scopes: [["read:users"]]})
+        {
+          id: ID!
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let user_scopes = |schema: &apollo_compiler::Schema| {
+        schema.types["User"]
+            .directives()
+            .get_all("federation__requiresScopes")
+            .map(|directive| {
+                directive
+                    .specified_argument_by_name("scopes")
+                    .expect("missing scopes")
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        user_scopes(a),
+        vec![r#"[["read:user"], ["admin", "read:all"]]"#]
+    );
+    assert_eq!(user_scopes(b), vec![r#"[["read:users"]]"#]);
+
+    let users = b.type_field("Query", "users").expect("missing field");
+    assert_eq!(
+        users
+            .directives
+            .get("federation__requiresScopes")
+            .and_then(|directive| directive.specified_argument_by_name("scopes"))
+            .cloned(),
+        Some(Node::new(Value::List(vec![])))
+    );
+}
+
+#[test]
+fn extracts_join_directive_policy() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+        {
+          query: Query
+        }
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION | SCALAR | ENUM
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          user: User @join__field(graph: A) @join__directive(graphs: [A], name: "policy", args: {policies: [["admin"], ["owner", "verified"]]})
+          users: [User] @join__field(graph: B)
+        }
+
+        type User
+          @join__type(graph: A, key: "id")
+          @join__type(graph: B, key: "id")
+          @join__directive(graphs: [B], name: "policy", args: {policies: [["read_users"]]})
+        {
+          id: ID!
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let policies = |directives: Option<&Node<apollo_compiler::ast::Directive>>| {
+        directives
+            .and_then(|directive| directive.specified_argument_by_name("policies"))
+            .map(|policies| policies.to_string())
+    };
+
+    let user = a.type_field("Query", "user").expect("missing field");
+    assert_eq!(
+        policies(user.directives.get("federation__policy")),
+        Some(r#"[["admin"], ["owner", "verified"]]"#.to_owned())
+    );
+    let users = b.type_field("Query", "users").expect("missing field");
+    assert_eq!(policies(users.directives.get("federation__policy")), None);
+
+    assert_eq!(
+        policies(
+            a.types["User"]
+                .directives()
+                .get("federation__policy")
+                .map(|directive| &directive.node)
+        ),
+        None
+    );
+    assert_eq!(
+        policies(
+            b.types["User"]
+                .directives()
+                .get("federation__policy")
+                .map(|directive| &directive.node)
+        ),
+        Some(r#"[["read_users"]]"#.to_owned())
+    );
+}
+
+#[test]
+fn extracts_interface_objects() {
+    let subgraphs = Supergraph::new(include_str!(
+        "query_plan/supergraphs/can_use_a_key_on_an_interface_object_type.graphql"
+    ))
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let s1 = subgraphs
+        .get("S1")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let s2 = subgraphs
+        .get("S2")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+
+    let ExtendedType::Interface(interface) = &s1.types["I"] else {
+        panic!("expected I to be an interface in S1");
+    };
+    assert!(!interface.directives.has("federation__interfaceObject"));
+
+    let ExtendedType::Object(interface_object) = &s2.types["I"] else {
+        panic!("expected I to be an object type in S2");
+    };
+    assert!(interface_object
+        .directives
+        .has("federation__interfaceObject"));
+    assert!(interface_object.directives.has("federation__key"));
+    assert!(interface_object.implements_interfaces.is_empty());
+    assert_eq!(
+        interface_object.fields.keys().collect::<Vec<_>>(),
+        ["id", "y"]
+    );
+    // The implementations of the interface are not known to the subgraph using @interfaceObject.
+    assert!(!s2.types.contains_key("A"));
+    assert!(!s2.types.contains_key("B"));
+}
+
+#[test]
+fn extracts_progressive_override_labels() {
+    let subgraphs = Supergraph::new(include_str!(
+        "query_plan/supergraphs/it_handles_progressive_override_on_root_fields.graphql"
+    ))
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let s1 = subgraphs
+        .get("s1")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let s2 = subgraphs
+        .get("s2")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+
+    let hello = s1.type_field("Query", "hello").expect("missing field");
+    assert!(!hello.directives.has("federation__override"));
+
+    let hello = s2.type_field("Query", "hello").expect("missing field");
+    let override_ = hello
+        .directives
+        .get("federation__override")
+        .expect("missing @override");
+    assert_eq!(
+        override_.specified_argument_by_name("from"),
+        Some(&Node::new(Value::String("s1".to_owned())))
+    );
+    assert_eq!(
+        override_.specified_argument_by_name("label"),
+        Some(&Node::new(Value::String("test".to_owned())))
+    );
+}
+
+#[test]
+fn extracts_a_single_subgraph() {
+    let supergraph = Supergraph::new(include_str!(
+        "query_plan/supergraphs/it_handles_progressive_override_on_root_fields.graphql"
+    ))
+    .expect("is supergraph");
+
+    let s2 = supergraph
+        .extract_subgraph("s2")
+        .expect("extracts subgraph");
+    assert_eq!(s2.name, "s2");
+    let subgraphs = supergraph.extract_subgraphs().expect("extracts subgraphs");
+    assert_eq!(
+        s2.schema.schema().to_string(),
+        subgraphs
+            .get("s2")
+            .expect("missing subgraph")
+            .schema
+            .schema()
+            .to_string()
+    );
+
+    let error = supergraph
+        .extract_subgraph("s3")
+        .expect_err("s3 is not a subgraph of the supergraph");
+    assert!(
+        error
+            .to_string()
+            .contains("No subgraph named \"s3\" is defined"),
+        "{error}"
+    );
+}
+
+#[test]
+fn extracts_tags_on_enum_values() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/tag/v0.3")
+        {
+          query: Query
+        }
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION | SCHEMA
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          a: Status @join__field(graph: A)
+          b: Status @join__field(graph: B)
+        }
+
+        enum Status
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          ACTIVE @join__enumValue(graph: A) @join__enumValue(graph: B)
+          ARCHIVED @join__enumValue(graph: A) @tag(name: "internal")
+          DELETED @join__enumValue(graph: B) @tag(name: "internal") @tag(name: "admin")
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let tags = |schema: &apollo_compiler::Schema, value_name: &str| {
+        let ExtendedType::Enum(status) = &schema.types["Status"] else {
+            panic!("expected Status to be an enum");
+        };
+        status.values[value_name]
+            .directives
+            .get_all("federation__tag")
+            .map(|tag| {
+                tag.specified_argument_by_name("name")
+                    .and_then(|name| name.as_str())
+                    .expect("missing tag name")
+                    .to_owned()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(tags(a, "ACTIVE").is_empty());
+    assert!(tags(b, "ACTIVE").is_empty());
+    assert_eq!(tags(a, "ARCHIVED"), ["internal"]);
+    assert_eq!(tags(b, "DELETED"), ["internal", "admin"]);
+}
+
+#[test]
+fn extracted_subgraphs_are_ordered_by_name() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+        {
+          query: Query
+        }
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          ZEBRA @join__graph(name: "zebra", url: "")
+          APPLE @join__graph(name: "apple", url: "")
+          MANGO @join__graph(name: "mango", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: ZEBRA)
+          @join__type(graph: APPLE)
+          @join__type(graph: MANGO)
+        {
+          zebra: Int @join__field(graph: ZEBRA)
+          apple: Int @join__field(graph: APPLE)
+          mango: Int @join__field(graph: MANGO)
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let names = subgraphs
+        .into_iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["apple", "mango", "zebra"]);
+}
+
+#[test]
+fn extracts_inaccessible_directives() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/inaccessible/v0.2", for: SECURITY)
+        {
+          query: Query
+        }
+
+        directive @inaccessible on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+
+        directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION | SCALAR | ENUM
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        type Internal
+          @join__type(graph: B)
+          @inaccessible
+        {
+          id: ID!
+        }
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__DirectiveArguments
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          A @join__graph(name: "a", url: "")
+          B @join__graph(name: "b", url: "")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          user(id: ID!, legacyId: Int @inaccessible): User @join__field(graph: A)
+          internal: Internal @join__field(graph: B) @inaccessible
+        }
+
+        enum Role
+          @join__type(graph: A)
+          @join__type(graph: B)
+        {
+          ADMIN @join__enumValue(graph: A) @join__enumValue(graph: B)
+          ROOT @join__enumValue(graph: A) @join__enumValue(graph: B) @inaccessible
+        }
+
+        type User
+          @join__type(graph: A, key: "id")
+          @join__type(graph: B, key: "id")
+        {
+          id: ID!
+          email: String @inaccessible
+          name: String @join__directive(graphs: [A], name: "inaccessible")
+          nickname: String @inaccessible @join__directive(graphs: [A], name: "inaccessible")
+          role: Role @join__field(graph: B)
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let a = subgraphs
+        .get("a")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let b = subgraphs
+        .get("b")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let field_is_inaccessible =
+        |schema: &apollo_compiler::Schema, type_name: &str, field_name: &str| {
+            schema
+                .type_field(type_name, field_name)
+                .expect("missing field")
+                .directives
+                .has("federation__inaccessible")
+        };
+
+    // Elements defined in a single subgraph
+    assert!(b.types["Internal"]
+        .directives()
+        .has("federation__inaccessible"));
+    assert!(!a.types.contains_key("Internal"));
+    assert!(field_is_inaccessible(b, "Query", "internal"));
+    let user = a.type_field("Query", "user").expect("missing field");
+    assert!(user
+        .argument_by_name("legacyId")
+        .expect("missing argument")
+        .directives
+        .has("federation__inaccessible"));
+    assert!(!user
+        .argument_by_name("id")
+        .expect("missing argument")
+        .directives
+        .has("federation__inaccessible"));
+
+    // Elements defined in both subgraphs, without saying which one marked them as inaccessible
+    assert!(field_is_inaccessible(a, "User", "email"));
+    assert!(field_is_inaccessible(b, "User", "email"));
+    assert!(!field_is_inaccessible(a, "User", "id"));
+    assert!(!field_is_inaccessible(b, "User", "id"));
+    let ExtendedType::Enum(role) = &b.types["Role"] else {
+        panic!("expected Role to be an enum");
+    };
+    assert!(role.values["ROOT"]
+        .directives
+        .has("federation__inaccessible"));
+    assert!(!role.values["ADMIN"]
+        .directives
+        .has("federation__inaccessible"));
+
+    // Inaccessible in one subgraph, but accessible in the other
+    assert!(field_is_inaccessible(a, "User", "name"));
+    assert!(!field_is_inaccessible(b, "User", "name"));
+    let nickname = a.type_field("User", "nickname").expect("missing field");
+    assert_eq!(
+        nickname
+            .directives
+            .get_all("federation__inaccessible")
+            .count(),
+        1
+    );
+    assert!(!field_is_inaccessible(b, "User", "nickname"));
+}
+
+#[test]
+fn extracts_context_arguments() {
+    let subgraphs = Supergraph::new(r#"
+        schema
+          @link(url: "https://specs.apollo.dev/link/v1.0")
+          @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+          @link(url: "https://specs.apollo.dev/context/v0.1")
+        {
+          query: Query
+        }
+
+        directive @context(name: String!) repeatable on INTERFACE | OBJECT | UNION
+
+        directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+        directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+        directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+        directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+        directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+        directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+        directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+        scalar context__context
+
+        input join__ContextArgument {
+          name: String!
+          type: String!
+          context: String!
+          selection: join__FieldValue!
+        }
+
+        scalar join__FieldSet
+
+        scalar join__FieldValue
+
+        enum join__Graph {
+          SUBGRAPH1 @join__graph(name: "Subgraph1", url: "https://Subgraph1")
+          SUBGRAPH2 @join__graph(name: "Subgraph2", url: "https://Subgraph2")
+        }
+
+        scalar link__Import
+
+        enum link__Purpose {
+          SECURITY
+          EXECUTION
+        }
+
+        type Query
+          @join__type(graph: SUBGRAPH1)
+          @join__type(graph: SUBGRAPH2)
+        {
+          t: T! @join__field(graph: SUBGRAPH1)
+          a: Int! @join__field(graph: SUBGRAPH2)
+        }
+
+        type T
+          @join__type(graph: SUBGRAPH1, key: "id")
+          @context(name: "Subgraph1__context")
+        {
+          id: ID!
+          u: U!
+          prop: String!
+        }
+
+        type U
+          @join__type(graph: SUBGRAPH1, key: "id")
+          @join__type(graph: SUBGRAPH2, key: "id")
+        {
+          id: ID!
+          field: Int! @join__field(graph: SUBGRAPH1, contextArguments: [{context: "Subgraph1__context", name: "a", type: "String", selection: "{ prop }"}])
+        }
+    "#)
+    .expect("is supergraph")
+    .extract_subgraphs()
+    .expect("extracts subgraphs");
+
+    let subgraph1 = subgraphs
+        .get("Subgraph1")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    let context = subgraph1.types["T"]
+        .directives()
+        .get("federation__context")
+        .expect("missing @context");
+    assert_eq!(
+        context.specified_argument_by_name("name"),
+        Some(&Node::new(Value::String("context".to_owned())))
+    );
+
+    let argument = subgraph1
+        .type_field("U", "field")
+        .expect("missing field")
+        .argument_by_name("a")
+        .expect("missing context argument");
+    assert_eq!(argument.ty.to_string(), "String");
+    let from_context = argument
+        .directives
+        .get("federation__fromContext")
+        .expect("missing @fromContext");
+    assert_eq!(
+        from_context.specified_argument_by_name("field"),
+        Some(&Node::new(Value::String("$context { prop }".to_owned())))
+    );
+
+    let subgraph2 = subgraphs
+        .get("Subgraph2")
+        .expect("missing subgraph")
+        .schema
+        .schema();
+    assert!(subgraph2.type_field("U", "field").is_err());
+}
+
 #[test]
 fn extracts_renamed_demand_control_directives() {
     let subgraphs = Supergraph::new(r#"
@@ -49,6 +49,8 @@ use super::listeners::ListenersAndRouters;
 use super::utils::PropagatingMakeSpan;
 use super::ListenAddrAndRouter;
 use super::ENDPOINT_CALLBACK;
+use crate::axum_factory::compression::peek_compressible;
+use crate::axum_factory::compression::CompressionOptions;
 use crate::axum_factory::compression::Compressor;
 use crate::axum_factory::listeners::get_extra_listeners;
 use crate::axum_factory::listeners::serve_router_on_listen_addr;
@@ -557,6 +559,7 @@ where
 {
     let early_cancel = configuration.supergraph.early_cancel;
     let experimental_log_on_broken_pipe = configuration.supergraph.experimental_log_on_broken_pipe;
+    let compression_min_size = configuration.supergraph.compression_min_size;
     let mut router = Router::new().route(
         &configuration.supergraph.sanitized_path(),
         get({
@@ -565,6 +568,7 @@ where
                     service.create().boxed(),
                     early_cancel,
                     experimental_log_on_broken_pipe,
+                    compression_min_size,
                     request,
                 )
             }
@@ -575,6 +579,7 @@ where
                     service.create().boxed(),
                     early_cancel,
                     experimental_log_on_broken_pipe,
+                    compression_min_size,
                     request,
                 )
             }
@@ -591,6 +596,7 @@ where
                         service.create().boxed(),
                         early_cancel,
                         experimental_log_on_broken_pipe,
+                        compression_min_size,
                         request,
                     )
                 }
@@ -602,6 +608,7 @@ where
                         service.create().boxed(),
                         early_cancel,
                         experimental_log_on_broken_pipe,
+                        compression_min_size,
                         request,
                     )
                 }
@@ -616,6 +623,7 @@ async fn handle_graphql(
     service: router::BoxService,
     early_cancel: bool,
     experimental_log_on_broken_pipe: bool,
+    compression_min_size: usize,
     http_request: Request<DecompressionBody<Body>>,
 ) -> impl IntoResponse {
     let _guard = SessionCountGuard::start();
@@ -626,11 +634,12 @@ async fn handle_graphql(
 
     let request: router::Request = http_request.into();
     let context = request.context.clone();
-    let accept_encoding = request
+    let opt_compressor = request
         .router_request
         .headers()
         .get(ACCEPT_ENCODING)
-        .cloned();
+        .and_then(|value| value.to_str().ok())
+        .and_then(Compressor::from_accept_encoding);
 
     let res = if early_cancel {
         service.oneshot(request).await
@@ -667,18 +676,24 @@ async fn handle_graphql(
         Ok(response) => {
             let (mut parts, body) = response.response.into_parts();
 
-            let opt_compressor = accept_encoding
-                .as_ref()
-                .and_then(|value| value.to_str().ok())
-                .and_then(|v| Compressor::new(v.split(',').map(|s| s.trim())));
             let body = match opt_compressor {
                 None => body,
                 Some(compressor) => {
-                    parts.headers.insert(
-                        CONTENT_ENCODING,
-                        HeaderValue::from_static(compressor.content_encoding()),
-                    );
-                    Body::wrap_stream(compressor.process(body.into()))
+                    let options = CompressionOptions {
+                        min_size: compression_min_size,
+                        record_metrics: true,
+                        ..Default::default()
+                    };
+                    let (body, compress) = peek_compressible(body.into(), options.min_size).await;
+                    if compress {
+                        parts.headers.insert(
+                            CONTENT_ENCODING,
+                            HeaderValue::from_static(compressor.content_encoding()),
+                        );
+                        Body::wrap_stream(compressor.process_with_options(body, options))
+                    } else {
+                        body.into_inner()
+                    }
                 }
             };
 
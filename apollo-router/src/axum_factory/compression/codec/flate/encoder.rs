@@ -88,8 +88,10 @@ impl Encode for FlateEncoder {
             }
         }
 
-        self.flushed = true;
-        Ok(!output.unwritten().is_empty())
+        // if the output is full there may still be data left in the encoder, so the flush has to
+        // run again once more room is available
+        self.flushed = !output.unwritten().is_empty();
+        Ok(self.flushed)
     }
 
     fn finish(
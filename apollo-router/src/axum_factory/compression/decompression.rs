@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+
+use bytes::Bytes;
+use flate2::write::DeflateDecoder;
+use flate2::write::GzDecoder;
+use futures::Stream;
+use futures::StreamExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tower::BoxError;
+use tracing::Instrument;
+
+use crate::services::router::body::RouterBody;
+
+/// Input is fed to the decoder in slices of this size, so that a single highly compressed chunk
+/// cannot expand far beyond the size limit before it is checked
+const DECODE_SLICE_LEN: usize = 1024;
+
+pub(crate) enum Decompressor {
+    Deflate(DeflateDecoder<Vec<u8>>),
+    Gzip(GzDecoder<Vec<u8>>),
+}
+
+impl Decompressor {
+    pub(crate) fn new(content_encoding: &str) -> Option<Self> {
+        match content_encoding.trim() {
+            "gzip" => Some(Decompressor::Gzip(GzDecoder::new(Vec::new()))),
+            "deflate" => Some(Decompressor::Deflate(DeflateDecoder::new(Vec::new()))),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn content_encoding(&self) -> &'static str {
+        match self {
+            Decompressor::Deflate(_) => "deflate",
+            Decompressor::Gzip(_) => "gzip",
+        }
+    }
+
+    /// Decodes the body, failing the stream once more than `max_size` bytes were produced
+    pub(crate) fn process(
+        mut self,
+        mut stream: RouterBody,
+        max_size: usize,
+    ) -> impl Stream<Item = Result<Bytes, BoxError>> {
+        let (tx, rx) = mpsc::channel(10);
+
+        tokio::task::spawn(
+            async move {
+                let mut decompressed_size = 0;
+
+                while let Some(data) = stream.next().await {
+                    let data = match data {
+                        Err(e) => {
+                            let _ = tx.send(Err(e.into())).await;
+                            return;
+                        }
+                        Ok(data) => data,
+                    };
+
+                    for slice in data.chunks(DECODE_SLICE_LEN) {
+                        let decoded = self.decode(slice);
+                        if !forward(&tx, decoded, &mut decompressed_size, max_size).await {
+                            return;
+                        }
+                    }
+                }
+
+                let decoded = self.finish();
+                forward(&tx, decoded, &mut decompressed_size, max_size).await;
+            }
+            .instrument(tracing::debug_span!("body_decompression")),
+        );
+        ReceiverStream::new(rx)
+    }
+
+    fn decode(&mut self, input: &[u8]) -> std::io::Result<Bytes> {
+        match self {
+            Decompressor::Deflate(d) => {
+                d.write_all(input)?;
+                Ok(std::mem::take(d.get_mut()).into())
+            }
+            Decompressor::Gzip(d) => {
+                d.write_all(input)?;
+                Ok(std::mem::take(d.get_mut()).into())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> std::io::Result<Bytes> {
+        match self {
+            Decompressor::Deflate(d) => {
+                d.try_finish()?;
+                Ok(std::mem::take(d.get_mut()).into())
+            }
+            Decompressor::Gzip(d) => {
+                d.try_finish()?;
+                Ok(std::mem::take(d.get_mut()).into())
+            }
+        }
+    }
+}
+
+/// Sends a decoded chunk to the receiver, returning `false` if decompression must stop
+async fn forward(
+    tx: &mpsc::Sender<Result<Bytes, BoxError>>,
+    decoded: std::io::Result<Bytes>,
+    decompressed_size: &mut usize,
+    max_size: usize,
+) -> bool {
+    match decoded {
+        Err(e) => {
+            let _ = tx.send(Err(e.into())).await;
+            false
+        }
+        Ok(buf) => {
+            *decompressed_size += buf.len();
+            if *decompressed_size > max_size {
+                let _ = tx
+                    .send(Err(format!(
+                        "decompressed body is larger than the maximum of {max_size} bytes"
+                    )
+                    .into()))
+                    .await;
+                return false;
+            }
+            if buf.is_empty() {
+                return true;
+            }
+            tx.send(Ok(buf)).await.is_ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::axum_factory::compression::Compressor;
+
+    async fn decompress(
+        decompressor: Decompressor,
+        body: RouterBody,
+        max_size: usize,
+    ) -> Result<Vec<u8>, BoxError> {
+        let mut stream = decompressor.process(body, max_size);
+        let mut output = Vec::new();
+        while let Some(buf) = stream.next().await {
+            output.extend_from_slice(&buf?);
+        }
+        Ok(output)
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let input = r#"{"query":"{ me { id name } }","variables":{}}"#.repeat(100);
+
+        for encoding in ["gzip", "deflate"] {
+            let compressor = Compressor::new([encoding].into_iter()).unwrap();
+            let mut compressed = Vec::new();
+            let mut stream = compressor.process(input.clone().into());
+            while let Some(buf) = stream.next().await {
+                compressed.extend_from_slice(&buf.unwrap());
+            }
+
+            let decompressor = Decompressor::new(encoding).unwrap();
+            assert_eq!(decompressor.content_encoding(), encoding);
+            let output = decompress(decompressor, compressed.into(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(output, input.as_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn unsupported_encoding() {
+        assert!(Decompressor::new("br").is_none());
+    }
+
+    #[tokio::test]
+    async fn decompression_bomb() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![0u8; 10 * 1024 * 1024]).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let decompressor = Decompressor::new("gzip").unwrap();
+        let error = decompress(decompressor, compressed.into(), 1024 * 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "decompressed body is larger than the maximum of 1048576 bytes"
+        );
+    }
+}
@@ -2,8 +2,11 @@ use brotli::enc::BrotliEncoderParams;
 use bytes::Bytes;
 use bytes::BytesMut;
 use flate2::Compression;
+use futures::future;
+use futures::stream;
 use futures::Stream;
 use futures::StreamExt;
+use hyper::body::HttpBody;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::BoxError;
@@ -18,49 +21,167 @@ use self::util::PartialBuffer;
 use crate::services::router::body::RouterBody;
 
 pub(crate) mod codec;
+pub(crate) mod decompression;
 pub(crate) mod unshared;
 pub(crate) mod util;
 
 const GZIP_HEADER_LEN: usize = 10;
+/// Default for [`CompressionOptions::min_size`], also used by the `supergraph.compression_min_size` option
+pub(crate) const DEFAULT_MIN_SIZE: usize = 32;
+
+/// Options used when compressing a body
+#[derive(Clone, Debug)]
+pub(crate) struct CompressionOptions {
+    /// Bodies smaller than this number of bytes are sent uncompressed, see [`peek_compressible`]
+    pub(crate) min_size: usize,
+    /// Room allocated for each compressed chunk on top of the input chunk's length
+    pub(crate) initial_chunk_buffer: usize,
+    /// Initial size of the buffer receiving the end of the compressed stream
+    pub(crate) finish_buffer: usize,
+    /// Number of compressed chunks waiting for the receiver before compression pauses
+    pub(crate) channel_capacity: usize,
+    /// Records the uncompressed and compressed sizes of the response once it is sent
+    pub(crate) record_metrics: bool,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+            // the buffer needs at least 10 bytes for a gzip header if we use gzip
+            initial_chunk_buffer: GZIP_HEADER_LEN,
+            finish_buffer: 1024,
+            channel_capacity: 10,
+            record_metrics: false,
+        }
+    }
+}
+
+/// Reads the beginning of the body to check whether it is large enough to be worth compressing.
+///
+/// Returns the body, with the data that was read put back in front of it, and `true` if it should
+/// be compressed. Streamed bodies with more than one chunk are always compressed.
+pub(crate) async fn peek_compressible(mut body: RouterBody, min_size: usize) -> (RouterBody, bool) {
+    if let Some(size) = HttpBody::size_hint(&body).exact() {
+        return (body, size >= min_size as u64);
+    }
+
+    match body.next().await {
+        None => (RouterBody::empty(), false),
+        Some(first) => {
+            let compress = match &first {
+                Ok(first) => first.len() >= min_size || !body.is_end_stream(),
+                Err(_) => false,
+            };
+            let body = RouterBody::wrap_stream(stream::once(future::ready(first)).chain(body));
+            (body, compress)
+        }
+    }
+}
 
 pub(crate) enum Compressor {
+    /// Copies the input unchanged
+    Identity,
     Deflate(DeflateEncoder),
     Gzip(GzipEncoder),
     Brotli(Box<BrotliEncoder>),
     Zstd(ZstdEncoder),
 }
 
+/// Compresses response bodies with the encoders used by the router for its own responses
+pub struct ResponseCompressor(Compressor);
+
+impl ResponseCompressor {
+    /// Creates a compressor for one of the `identity`, `gzip`, `deflate`, `br` or `zstd` encodings
+    ///
+    /// Returns `None` if the encoding is not supported.
+    pub fn new(encoding: &str) -> Option<Self> {
+        Compressor::from_name(encoding.trim()).map(Self)
+    }
+
+    /// Creates a compressor for the preferred supported encoding of an `Accept-Encoding` header
+    ///
+    /// Returns `None` if the body should not be compressed.
+    pub fn from_accept_encoding(header: &str) -> Option<Self> {
+        Compressor::from_accept_encoding(header).map(Self)
+    }
+
+    /// The value to use for the `Content-Encoding` header of the compressed response
+    pub fn content_encoding(&self) -> &'static str {
+        self.0.content_encoding()
+    }
+
+    /// Compresses the body as a stream of chunks
+    pub fn process(self, body: hyper::Body) -> impl Stream<Item = Result<Bytes, BoxError>> {
+        self.0.process(body.into())
+    }
+}
+
 impl Compressor {
-    pub(crate) fn new<'a, It>(it: It) -> Option<Self>
+    pub(crate) fn new<'a, It>(mut it: It) -> Option<Self>
     where
         It: Iterator<Item = &'a str>,
         It: 'a,
     {
-        for s in it {
-            match s {
-                "gzip" => return Some(Compressor::Gzip(GzipEncoder::new(Compression::fast()))),
-                "deflate" => {
-                    return Some(Compressor::Deflate(
-                        DeflateEncoder::new(Compression::fast()),
-                    ))
-                }
-                // FIXME: find the "fast" brotli encoder params
-                "br" => {
-                    return Some(Compressor::Brotli(Box::new(BrotliEncoder::new(
-                        BrotliEncoderParams::default(),
-                    ))))
-                }
-                "zstd" => {
-                    return Some(Compressor::Zstd(ZstdEncoder::new(zstd_safe::min_c_level())))
-                }
-                _ => {}
+        it.find_map(Self::from_name)
+    }
+
+    /// Selects the supported encoding with the highest quality value in an `Accept-Encoding`
+    /// header, keeping the header order on ties. Encodings are matched case-insensitively, those
+    /// with `q=0` are refused, and malformed quality values are ignored.
+    ///
+    /// Returns `None` when the body should be sent uncompressed. This is also the case when the
+    /// client refuses `identity`, as an uncompressed response is more useful to it than an error.
+    pub(crate) fn from_accept_encoding(header: &str) -> Option<Self> {
+        let mut best: Option<(String, f32)> = None;
+
+        for entry in header.split(',') {
+            let mut parts = entry.split(';');
+            let encoding = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| {
+                    let (name, value) = param.split_once('=')?;
+                    if !name.trim().eq_ignore_ascii_case("q") {
+                        return None;
+                    }
+                    value.trim().parse::<f32>().ok()
+                })
+                .unwrap_or(1.0);
+
+            // `identity` and `*` only matter to refuse uncompressed responses, which are sent anyway
+            if quality > 0.0
+                && encoding != "identity"
+                && Self::from_name(&encoding).is_some()
+                && best
+                    .as_ref()
+                    .map_or(true, |(_, best_quality)| quality > *best_quality)
+            {
+                best = Some((encoding, quality));
             }
         }
-        None
+
+        best.and_then(|(encoding, _)| Self::from_name(&encoding))
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "identity" => Some(Compressor::Identity),
+            "gzip" => Some(Compressor::Gzip(GzipEncoder::new(Compression::fast()))),
+            "deflate" => Some(Compressor::Deflate(
+                DeflateEncoder::new(Compression::fast()),
+            )),
+            // FIXME: find the "fast" brotli encoder params
+            "br" => Some(Compressor::Brotli(Box::new(BrotliEncoder::new(
+                BrotliEncoderParams::default(),
+            )))),
+            "zstd" => Some(Compressor::Zstd(ZstdEncoder::new(zstd_safe::min_c_level()))),
+            _ => None,
+        }
     }
 
     pub(crate) fn content_encoding(&self) -> &'static str {
         match self {
+            Compressor::Identity => "identity",
             Compressor::Deflate(_) => "deflate",
             Compressor::Gzip(_) => "gzip",
             Compressor::Brotli(_) => "br",
@@ -68,29 +189,47 @@ impl Compressor {
         }
     }
 
-    pub(crate) fn process(
+    pub(crate) fn process(self, stream: RouterBody) -> impl Stream<Item = Result<Bytes, BoxError>> {
+        self.process_with_options(stream, CompressionOptions::default())
+    }
+
+    pub(crate) fn process_with_options(
         mut self,
         mut stream: RouterBody,
-    ) -> impl Stream<Item = Result<Bytes, BoxError>>
-where {
-        let (tx, rx) = mpsc::channel(10);
+        options: CompressionOptions,
+    ) -> impl Stream<Item = Result<Bytes, BoxError>> {
+        // the task stops as soon as a send fails, which means the receiver was dropped
+        let (tx, rx) = mpsc::channel(options.channel_capacity.max(1));
+        let encoding = self.content_encoding();
+        let record_metrics = options.record_metrics;
 
         tokio::task::spawn(
             async move {
+                // the sender outlives this, so the sizes are recorded before the receiver sees the
+                // end of the stream
+                let mut sizes = ResponseSizes {
+                    encoding,
+                    record_metrics,
+                    uncompressed: 0,
+                    compressed: 0,
+                };
+                // compressed data is split off this buffer before being sent, and its allocation
+                // is reclaimed by the next resize once the receiver has dropped the previous chunk
+                let mut scratch = BytesMut::new();
+
                 while let Some(data) = stream.next().await {
                     match data {
                         Err(e) => {
-                            if (tx.send(Err(e.into())).await).is_err() {
-                                return;
-                            }
+                            // the compressed stream cannot be recovered after an input error, so
+                            // we forward it and stop without writing the trailer
+                            let _ = tx.send(Err(e.into())).await;
+                            return;
                         }
                         Ok(data) => {
-                            // the buffer needs at least 10 bytes for a gzip header if we use gzip, then more
-                            // room to store the data itself
-                            let mut buf = BytesMut::zeroed(GZIP_HEADER_LEN + data.len());
+                            scratch.resize(options.initial_chunk_buffer + data.len(), 0);
 
                             let mut partial_input = PartialBuffer::new(&*data);
-                            let mut partial_output = PartialBuffer::new(&mut buf);
+                            let mut partial_output = PartialBuffer::new(&mut scratch);
                             loop {
                                 if let Err(e) = self.encode(&mut partial_input, &mut partial_output)
                                 {
@@ -102,9 +241,20 @@ where {
                                     // there was not enough space in the output buffer to compress everything,
                                     // so we resize and add more data
                                     if partial_output.unwritten().is_empty() {
-                                        partial_output.extend(partial_input.unwritten().len() / 10);
+                                        // with small initial buffers the remaining input can be
+                                        // shorter than 10 bytes, so always grow by a minimum amount
+                                        partial_output.extend(
+                                            (partial_input.unwritten().len() / 10)
+                                                .max(GZIP_HEADER_LEN),
+                                        );
                                     }
                                 } else {
+                                    // flushing into a full buffer would leave the end of the chunk
+                                    // in the encoder until the next chunk
+                                    if partial_output.unwritten().is_empty() {
+                                        partial_output.extend(GZIP_HEADER_LEN);
+                                    }
+
                                     loop {
                                         match self.flush(&mut partial_output) {
                                             Err(e) => {
@@ -116,8 +266,12 @@ where {
                                                     break;
                                                 }
                                                 if partial_output.unwritten().is_empty() {
-                                                    partial_output
-                                                        .extend(partial_output.written().len());
+                                                    partial_output.extend(
+                                                        partial_output
+                                                            .written()
+                                                            .len()
+                                                            .max(GZIP_HEADER_LEN),
+                                                    );
                                                 }
                                             }
                                         }
@@ -125,11 +279,13 @@ where {
 
                                     let len = partial_output.written().len();
                                     let _ = partial_output.into_inner();
-                                    buf.resize(len, 0);
+                                    scratch.truncate(len);
 
-                                    if (tx.send(Ok(buf.freeze())).await).is_err() {
+                                    if (tx.send(Ok(scratch.split().freeze())).await).is_err() {
                                         return;
                                     }
+                                    sizes.uncompressed += data.len() as u64;
+                                    sizes.compressed += len as u64;
                                     break;
                                 }
                             }
@@ -137,36 +293,66 @@ where {
                     }
                 }
 
+                // finish is called until it reports that the end of the stream is written, growing
+                // the buffer when it is full so the trailer is never truncated
+                scratch.resize(options.finish_buffer.max(1), 0);
+                let mut partial_output = PartialBuffer::new(&mut scratch);
                 loop {
-                    let buf = BytesMut::zeroed(1024);
-                    let mut partial_output = PartialBuffer::new(buf);
-
                     match self.finish(&mut partial_output) {
                         Err(e) => {
                             let _ = tx.send(Err(e.into())).await;
-                            break;
+                            return;
                         }
-                        Ok(is_flushed) => {
-                            let len = partial_output.written().len();
-
-                            let mut buf = partial_output.into_inner();
-                            buf.resize(len, 0);
-                            if (tx.send(Ok(buf.freeze())).await).is_err() {
-                                return;
-                            }
-                            if is_flushed {
-                                break;
+                        Ok(true) => break,
+                        Ok(false) => {
+                            if partial_output.unwritten().is_empty() {
+                                partial_output.extend(partial_output.written().len());
                             }
                         }
                     }
                 }
+
+                let len = partial_output.written().len();
+                let _ = partial_output.into_inner();
+                scratch.truncate(len);
+                if !scratch.is_empty() && tx.send(Ok(scratch.split().freeze())).await.is_ok() {
+                    sizes.compressed += len as u64;
+                }
             }
             .instrument(tracing::debug_span!("body_compression")),
         );
+
         ReceiverStream::new(rx)
     }
 }
 
+/// Sizes of the data sent for one response, recorded once the compression task stops
+struct ResponseSizes {
+    encoding: &'static str,
+    record_metrics: bool,
+    uncompressed: u64,
+    compressed: u64,
+}
+
+impl Drop for ResponseSizes {
+    fn drop(&mut self) {
+        if self.record_metrics {
+            u64_counter!(
+                "apollo.router.http.response.uncompressed_bytes",
+                "Size of response bodies before compression",
+                self.uncompressed,
+                "encoding" = self.encoding
+            );
+            u64_counter!(
+                "apollo.router.http.response.compressed_bytes",
+                "Size of response bodies after compression",
+                self.compressed,
+                "encoding" = self.encoding
+            );
+        }
+    }
+}
+
 impl Encode for Compressor {
     fn encode(
         &mut self,
@@ -174,6 +360,10 @@ impl Encode for Compressor {
         output: &mut PartialBuffer<impl AsRef<[u8]> + AsMut<[u8]>>,
     ) -> std::io::Result<()> {
         match self {
+            Compressor::Identity => {
+                output.copy_unwritten_from(input);
+                Ok(())
+            }
             Compressor::Deflate(e) => e.encode(input, output),
             Compressor::Gzip(e) => e.encode(input, output),
             Compressor::Brotli(e) => e.encode(input, output),
@@ -186,6 +376,7 @@ impl Encode for Compressor {
         output: &mut PartialBuffer<impl AsRef<[u8]> + AsMut<[u8]>>,
     ) -> std::io::Result<bool> {
         match self {
+            Compressor::Identity => Ok(true),
             Compressor::Deflate(e) => e.flush(output),
             Compressor::Gzip(e) => e.flush(output),
             Compressor::Brotli(e) => e.flush(output),
@@ -198,6 +389,7 @@ impl Encode for Compressor {
         output: &mut PartialBuffer<impl AsRef<[u8]> + AsMut<[u8]>>,
     ) -> std::io::Result<bool> {
         match self {
+            Compressor::Identity => Ok(true),
             Compressor::Deflate(e) => e.finish(output),
             Compressor::Gzip(e) => e.finish(output),
             Compressor::Brotli(e) => e.finish(output),
@@ -208,12 +400,52 @@ impl Encode for Compressor {
 
 #[cfg(test)]
 mod tests {
+    use async_compression::tokio::write::BrotliDecoder;
     use async_compression::tokio::write::GzipDecoder;
     use futures::stream;
     use rand::Rng;
     use tokio::io::AsyncWriteExt;
 
     use super::*;
+    use crate::graphql;
+    use crate::metrics::FutureMetricsExt;
+    use crate::protocols::multipart::Multipart;
+    use crate::protocols::multipart::ProtocolMode;
+
+    fn negotiated(header: &str) -> Option<&'static str> {
+        Compressor::from_accept_encoding(header).map(|compressor| compressor.content_encoding())
+    }
+
+    #[test]
+    fn accept_encoding_quality_values() {
+        assert_eq!(negotiated("gzip, deflate, br"), Some("gzip"));
+        assert_eq!(
+            negotiated("gzip;q=0.5, deflate;q=0.8, br;q=0"),
+            Some("deflate")
+        );
+        assert_eq!(negotiated("gzip;q=0.5, br"), Some("br"));
+        assert_eq!(negotiated("gzip;q=0, unknown"), None);
+        assert_eq!(negotiated(""), None);
+        assert_eq!(negotiated("identity"), None);
+        assert_eq!(negotiated("identity;q=0, gzip"), Some("gzip"));
+        assert_eq!(negotiated("identity, *;q=0"), None);
+        // Refusing everything the router supports still gets an uncompressed response
+        assert_eq!(negotiated("identity;q=0, gzip;q=0"), None);
+        assert_eq!(negotiated("*;q=0"), None);
+    }
+
+    #[test]
+    fn accept_encoding_is_case_insensitive() {
+        assert_eq!(negotiated("GZIP"), Some("gzip"));
+        assert_eq!(negotiated("gzip;Q=0.5, Br;q=0.8"), Some("br"));
+        assert_eq!(negotiated("gzip;Q=0"), None);
+    }
+
+    #[test]
+    fn accept_encoding_ignores_malformed_quality_values() {
+        assert_eq!(negotiated("gzip;q=high"), Some("gzip"));
+        assert_eq!(negotiated("gzip;q=, br;q=0.5"), Some("gzip"));
+    }
 
     #[tokio::test]
     async fn finish() {
@@ -240,6 +472,258 @@ mod tests {
         assert!(stream.next().await.is_none());
     }
 
+    #[tokio::test]
+    async fn large_input_round_trip() {
+        use std::io::Read;
+
+        let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+
+        let mut rng = rand::thread_rng();
+        let input = std::iter::repeat(())
+            .map(|_| rng.gen::<u8>())
+            .take(4 * 1024 * 1024)
+            .collect::<Vec<_>>();
+        let body: RouterBody = RouterBody::wrap_stream(stream::iter(
+            input
+                .chunks(64 * 1024)
+                .map(|chunk| Ok::<_, BoxError>(Bytes::copy_from_slice(chunk)))
+                .collect::<Vec<_>>(),
+        ));
+
+        let mut stream = compressor.process(body);
+        let mut compressed = Vec::new();
+        while let Some(buf) = stream.next().await {
+            compressed.extend_from_slice(&buf.unwrap());
+        }
+
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, input);
+    }
+
+    #[tokio::test]
+    async fn custom_buffer_sizes() {
+        let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+
+        let body: RouterBody = r#"{"data":{"me":{"id":"1","name":"Ada Lovelace"}}}"#.into();
+
+        let mut stream = compressor.process_with_options(
+            body,
+            CompressionOptions {
+                initial_chunk_buffer: 1,
+                finish_buffer: 1,
+                ..Default::default()
+            },
+        );
+        let mut decoder = GzipDecoder::new(Vec::new());
+
+        while let Some(buf) = stream.next().await {
+            decoder.write_all(&buf.unwrap()).await.unwrap();
+        }
+
+        decoder.shutdown().await.unwrap();
+        assert_eq!(
+            decoder.into_inner(),
+            br#"{"data":{"me":{"id":"1","name":"Ada Lovelace"}}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn small_bodies_are_not_compressed() {
+        let (body, compress) = peek_compressible(vec![0u8, 1, 2, 3].into(), 32).await;
+        assert!(!compress);
+        assert_eq!(body.to_bytes().await.unwrap(), vec![0u8, 1, 2, 3]);
+
+        let (body, compress) = peek_compressible(vec![0u8; 64].into(), 32).await;
+        assert!(compress);
+        assert_eq!(body.to_bytes().await.unwrap().len(), 64);
+
+        let (body, compress) = peek_compressible(
+            RouterBody::wrap_stream(stream::iter(vec![
+                Ok::<_, BoxError>(Bytes::from("small")),
+                Ok(Bytes::from("chunks")),
+            ])),
+            32,
+        )
+        .await;
+        assert!(compress);
+        assert_eq!(body.to_bytes().await.unwrap(), "smallchunks");
+    }
+
+    #[tokio::test]
+    async fn slow_receiver() {
+        let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+
+        let chunks = (0..20)
+            .map(|i| Ok::<_, BoxError>(Bytes::from(format!("chunk {i}\n"))))
+            .collect::<Vec<_>>();
+        let mut stream = compressor.process_with_options(
+            RouterBody::wrap_stream(stream::iter(chunks)),
+            CompressionOptions {
+                channel_capacity: 1,
+                ..Default::default()
+            },
+        );
+        let mut decoder = GzipDecoder::new(Vec::new());
+
+        while let Some(buf) = stream.next().await {
+            tokio::task::yield_now().await;
+            decoder.write_all(&buf.unwrap()).await.unwrap();
+        }
+
+        decoder.shutdown().await.unwrap();
+        let expected = (0..20).map(|i| format!("chunk {i}\n")).collect::<String>();
+        assert_eq!(decoder.into_inner(), expected.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn response_compressor() {
+        assert!(ResponseCompressor::new("unknown").is_none());
+        assert!(ResponseCompressor::from_accept_encoding("identity").is_none());
+
+        let compressor = ResponseCompressor::from_accept_encoding("gzip;q=0.2, deflate").unwrap();
+        assert_eq!(compressor.content_encoding(), "deflate");
+
+        let compressor = ResponseCompressor::new("gzip").unwrap();
+        assert_eq!(compressor.content_encoding(), "gzip");
+
+        let mut stream = compressor.process(hyper::Body::from("hello world"));
+        let mut decoder = GzipDecoder::new(Vec::new());
+        while let Some(buf) = stream.next().await {
+            decoder.write_all(&buf.unwrap()).await.unwrap();
+        }
+
+        decoder.shutdown().await.unwrap();
+        assert_eq!(decoder.into_inner(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn identity() {
+        let compressor = Compressor::new(["identity"].into_iter()).unwrap();
+        assert_eq!(compressor.content_encoding(), "identity");
+
+        let body: RouterBody = RouterBody::wrap_stream(stream::iter(vec![
+            Ok::<_, BoxError>(Bytes::from("hello ")),
+            Ok(Bytes::from("world")),
+        ]));
+
+        let mut stream = compressor.process(body);
+        assert_eq!(stream.next().await.unwrap().unwrap(), "hello ");
+        assert_eq!(stream.next().await.unwrap().unwrap(), "world");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        async {
+            let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+
+            let body: RouterBody = RouterBody::wrap_stream(stream::iter(vec![
+                Ok::<_, BoxError>(Bytes::from("a".repeat(1000))),
+                Ok(Bytes::from("b".repeat(500))),
+            ]));
+
+            let mut stream = compressor.process_with_options(
+                body,
+                CompressionOptions {
+                    record_metrics: true,
+                    ..Default::default()
+                },
+            );
+            let mut compressed_len = 0u64;
+            while let Some(buf) = stream.next().await {
+                compressed_len += buf.unwrap().len() as u64;
+            }
+
+            assert_counter!(
+                "apollo.router.http.response.uncompressed_bytes",
+                1500u64,
+                "encoding" = "gzip"
+            );
+            assert_counter!(
+                "apollo.router.http.response.compressed_bytes",
+                compressed_len,
+                "encoding" = "gzip"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn flush_into_full_buffer() {
+        use std::io::Write;
+
+        let mut rng = rand::thread_rng();
+        let chunks = (0..4)
+            .map(|_| {
+                std::iter::repeat(())
+                    .map(|_| rng.gen::<u8>())
+                    .take(100)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // random data is stored uncompressed with a few bytes of framing, so one of these
+        // buffer sizes fills the output exactly before the flush
+        for initial_chunk_buffer in 0..16 {
+            let compressor = Compressor::new(["deflate"].into_iter()).unwrap();
+            let body = RouterBody::wrap_stream(stream::iter(
+                chunks
+                    .iter()
+                    .map(|chunk| Ok::<_, BoxError>(Bytes::from(chunk.clone())))
+                    .collect::<Vec<_>>(),
+            ));
+            let mut stream = compressor.process_with_options(
+                body,
+                CompressionOptions {
+                    initial_chunk_buffer,
+                    ..Default::default()
+                },
+            );
+
+            let mut decoder = flate2::write::DeflateDecoder::new(Vec::new());
+            for (i, _) in chunks.iter().enumerate() {
+                decoder
+                    .write_all(&stream.next().await.unwrap().unwrap())
+                    .unwrap();
+                decoder.flush().unwrap();
+                assert_eq!(decoder.get_ref(), &chunks[..=i].concat());
+            }
+            while let Some(buf) = stream.next().await {
+                decoder.write_all(&buf.unwrap()).unwrap();
+            }
+            assert_eq!(decoder.finish().unwrap(), chunks.concat());
+        }
+    }
+
+    #[tokio::test]
+    async fn defer_increments_are_flushed_without_waiting_for_the_next_one() {
+        let primary = graphql::Response::builder()
+            .data(serde_json_bytes::json!({"me": {"id": "1"}}))
+            .has_next(true)
+            .build();
+        // the upstream never sends the deferred part, so the primary response must be readable
+        // from its own compressed chunk
+        let responses = stream::iter(vec![primary]).chain(stream::pending());
+        let body = RouterBody::wrap_stream(Multipart::new(responses, ProtocolMode::Defer));
+
+        let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+        let mut stream = compressor.process(body);
+        let mut decoder = GzipDecoder::new(Vec::new());
+
+        let first = stream.next().await.unwrap().unwrap();
+        decoder.write_all(&first).await.unwrap();
+        decoder.flush().await.unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(decoder.get_ref()).unwrap(),
+            "\r\n--graphql\r\ncontent-type: application/json\r\n\r\n{\"data\":{\"me\":{\"id\":\"1\"}},\"hasNext\":true}\r\n--graphql"
+        );
+    }
+
     #[tokio::test]
     async fn small_input() {
         let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
@@ -261,6 +745,66 @@ mod tests {
         assert!(stream.next().await.is_none());
     }
 
+    #[tokio::test]
+    async fn brotli() {
+        let compressor = Compressor::new(["br"].into_iter()).unwrap();
+        assert_eq!(compressor.content_encoding(), "br");
+
+        let mut rng = rand::thread_rng();
+        let input = std::iter::repeat(())
+            .map(|_| rng.gen_range(0u8..3))
+            .take(5000)
+            .collect::<Vec<_>>();
+        let body: RouterBody = input.clone().into();
+
+        let mut stream = compressor.process(body);
+        let mut decoder = BrotliDecoder::new(Vec::new());
+
+        while let Some(buf) = stream.next().await {
+            decoder.write_all(&buf.unwrap()).await.unwrap();
+        }
+
+        decoder.shutdown().await.unwrap();
+        assert_eq!(decoder.into_inner(), input);
+    }
+
+    #[tokio::test]
+    async fn zstd() {
+        let compressor = Compressor::new(["zstd"].into_iter()).unwrap();
+        assert_eq!(compressor.content_encoding(), "zstd");
+
+        let mut rng = rand::thread_rng();
+        let input = std::iter::repeat(())
+            .map(|_| rng.gen_range(0u8..3))
+            .take(5000)
+            .collect::<Vec<_>>();
+        let body: RouterBody = input.clone().into();
+
+        let mut stream = compressor.process(body);
+        let mut compressed = Vec::new();
+        while let Some(buf) = stream.next().await {
+            compressed.extend_from_slice(&buf.unwrap());
+        }
+
+        assert_eq!(::zstd::stream::decode_all(&compressed[..]).unwrap(), input);
+    }
+
+    #[tokio::test]
+    async fn input_error() {
+        let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
+
+        let body: RouterBody = RouterBody::wrap_stream(stream::iter(vec![
+            Ok::<_, BoxError>(Bytes::from("hello")),
+            Err("input error".into()),
+            Ok(Bytes::from("world")),
+        ]));
+
+        let mut stream = compressor.process(body);
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+
     #[tokio::test]
     async fn gzip_header_writing() {
         let compressor = Compressor::new(["gzip"].into_iter()).unwrap();
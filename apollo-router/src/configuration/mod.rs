@@ -42,7 +42,10 @@ use self::expansion::Expansion;
 pub(crate) use self::experimental::Discussed;
 pub(crate) use self::schema::generate_config_schema;
 pub(crate) use self::schema::generate_upgrade;
+pub(crate) use self::schema::generate_upgrade_changes;
 use self::subgraph::SubgraphConfiguration;
+pub use self::upgrade::ConfigurationMigration;
+use crate::axum_factory::compression::DEFAULT_MIN_SIZE;
 use crate::cache::DEFAULT_CACHE_CAPACITY;
 use crate::configuration::schema::Mode;
 use crate::graphql;
@@ -105,6 +108,9 @@ pub enum ConfigurationError {
     /// could not migrate configuration: {error}.
     MigrationFailure { error: String },
 
+    /// invalid configuration migration {file}: {error}
+    InvalidMigration { file: String, error: String },
+
     /// could not load certificate authorities: {error}
     CertificateAuthorities { error: String },
 }
@@ -588,7 +594,24 @@ impl FromStr for Configuration {
     type Err = ConfigurationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        schema::validate_yaml_configuration(s, Expansion::default()?, Mode::Upgrade)?.validate()
+        Self::from_str_with_migrations(s, &[])
+    }
+}
+
+impl Configuration {
+    /// Parses the configuration like [`FromStr`], applying `extra_migrations` after the router's
+    /// own migrations.
+    pub(crate) fn from_str_with_migrations(
+        s: &str,
+        extra_migrations: &[ConfigurationMigration],
+    ) -> Result<Self, ConfigurationError> {
+        schema::validate_yaml_configuration_with_migrations(
+            s,
+            Expansion::default()?,
+            Mode::Upgrade,
+            extra_migrations,
+        )?
+        .validate()
     }
 }
 
@@ -708,6 +731,11 @@ pub(crate) struct Supergraph {
     /// Log a message if the client closes the connection before the response is sent.
     /// Default: false.
     pub(crate) experimental_log_on_broken_pipe: bool,
+
+    /// Responses and subgraph requests with a body smaller than this number of bytes are sent
+    /// uncompressed, as compression would only add overhead.
+    /// Default: 32
+    pub(crate) compression_min_size: usize,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
@@ -746,6 +774,7 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        compression_min_size: Option<usize>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(default_graphql_listen),
@@ -765,6 +794,7 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            compression_min_size: compression_min_size.unwrap_or(DEFAULT_MIN_SIZE),
         }
     }
 }
@@ -783,6 +813,7 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        compression_min_size: Option<usize>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(test_listen),
@@ -802,6 +833,7 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            compression_min_size: compression_min_size.unwrap_or(DEFAULT_MIN_SIZE),
         }
     }
 }
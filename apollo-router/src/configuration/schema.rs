@@ -25,8 +25,10 @@ use super::plugins;
 use super::yaml;
 use super::Configuration;
 use super::ConfigurationError;
+use super::ConfigurationMigration;
 use super::APOLLO_PLUGIN_PREFIX;
 pub(crate) use crate::configuration::upgrade::generate_upgrade;
+pub(crate) use crate::configuration::upgrade::generate_upgrade_changes;
 pub(crate) use crate::configuration::upgrade::upgrade_configuration;
 
 const NUMBER_OF_PREVIOUS_LINES_TO_DISPLAY: usize = 5;
@@ -103,6 +105,17 @@ pub(crate) fn validate_yaml_configuration(
     raw_yaml: &str,
     expansion: Expansion,
     migration: Mode,
+) -> Result<Configuration, ConfigurationError> {
+    validate_yaml_configuration_with_migrations(raw_yaml, expansion, migration, &[])
+}
+
+/// Validates the configuration like [`validate_yaml_configuration`], applying `extra_migrations`
+/// after the router's own migrations when upgrading.
+pub(crate) fn validate_yaml_configuration_with_migrations(
+    raw_yaml: &str,
+    expansion: Expansion,
+    migration: Mode,
+    extra_migrations: &[ConfigurationMigration],
 ) -> Result<Configuration, ConfigurationError> {
     let defaulted_yaml = if raw_yaml.trim().is_empty() {
         "plugins:".to_string()
@@ -134,7 +147,7 @@ pub(crate) fn validate_yaml_configuration(
     });
 
     if migration == Mode::Upgrade {
-        let upgraded = upgrade_configuration(&yaml, true)?;
+        let upgraded = upgrade_configuration(&yaml, true, extra_migrations)?;
         let expanded_yaml = expansion.expand(&upgraded)?;
         if schema.validate(&expanded_yaml).is_ok() {
             yaml = upgraded;
@@ -589,6 +589,7 @@ fn upgrade_old_configuration() {
             let new_config = crate::configuration::upgrade::upgrade_configuration(
                 &serde_yaml::from_str(&input).expect("config must be valid yaml"),
                 true,
+                &[],
             )
             .expect("configuration could not be updated");
             let new_config =
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Write as _;
 use std::str::FromStr;
 
@@ -6,22 +7,25 @@ use proteus::Parser;
 use proteus::TransformBuilder;
 use rust_embed::RustEmbed;
 use serde::Deserialize;
+use serde::Serialize;
 use serde_json::Value;
 use tracing_core::Level;
 
 use crate::error::ConfigurationError;
 
+mod document;
+
 #[derive(RustEmbed)]
 #[folder = "src/configuration/migrations"]
 struct Asset;
 
-#[derive(Deserialize, buildstructor::Builder)]
+#[derive(Clone, Deserialize, buildstructor::Builder)]
 struct Migration {
     description: String,
     actions: Vec<Action>,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum Action {
     Add {
@@ -29,6 +33,14 @@ enum Action {
         name: String,
         value: Value,
     },
+    /// Append `value` to the array at `path`. Nothing is appended if there is no array at `path`,
+    /// or if `skip_if_present` is set and the array already contains `value`.
+    Append {
+        path: String,
+        value: Value,
+        #[serde(default)]
+        skip_if_present: bool,
+    },
     Delete {
         path: String,
     },
@@ -40,11 +52,29 @@ enum Action {
         from: String,
         to: String,
     },
+    /// Deep merge the object at `from` into the object at `to`, keeping the keys of `to` that are
+    /// not in `from`. `from` is left in place.
+    Merge {
+        from: String,
+        to: String,
+    },
     Change {
         path: String,
         from: Value,
         to: Value,
     },
+    /// Convert the value at `path` to another type, eg. `"5"` to `5`. The migration fails if the
+    /// value can't be converted.
+    Coerce {
+        path: String,
+        to_type: CoercionType,
+    },
+    /// Apply the actions of `then` only if the value at `path` equals `equals`.
+    When {
+        path: String,
+        equals: Value,
+        then: Vec<Action>,
+    },
     /// Don't migrate anything, just log a better message before the parsing error.
     /// It can be useful when you're moving a feature from experimental to GA and it is not backward compatible
     Log {
@@ -54,150 +84,829 @@ enum Action {
     },
 }
 
-const REMOVAL_VALUE: &str = "__PLEASE_DELETE_ME";
-const REMOVAL_EXPRESSION: &str = r#"const("__PLEASE_DELETE_ME")"#;
+/// A configuration migration that is applied along with the router's own migrations, eg. to
+/// upgrade the configuration of a custom plugin.
+///
+/// Migrations are passed to the router with `Executable::builder().configuration_migration(...)`
+/// or `RouterHttpServer::builder().configuration_migration(...)`. They are applied after all of
+/// the router's own migrations, ordered by name, so they always see configuration that is in the
+/// router's current format. They use the same YAML format as the router's migrations, described in
+/// the router's `src/configuration/migrations/README.md`.
+#[derive(Clone)]
+pub struct ConfigurationMigration {
+    name: String,
+    migration: Migration,
+}
+
+impl ConfigurationMigration {
+    /// Parses a migration. `name` identifies the migration and decides the order that it is
+    /// applied in; prefixing it with a number, eg. `0001-rename-field`, keeps that order obvious.
+    ///
+    /// Returns an error if the migration is not valid, eg. if one of its paths can't be parsed, so
+    /// that applying it can only fail on the configuration it is applied to.
+    pub fn from_yaml(name: &str, yaml: &str) -> Result<Self, ConfigurationError> {
+        Ok(Self {
+            name: name.to_string(),
+            migration: parse_migration(name, yaml.as_bytes())?,
+        })
+    }
+}
 
+/// Upgrades the configuration with the router's own migrations, followed by `extra_migrations`.
 pub(crate) fn upgrade_configuration(
     config: &serde_json::Value,
     log_warnings: bool,
+    extra_migrations: &[ConfigurationMigration],
 ) -> Result<serde_json::Value, super::ConfigurationError> {
-    // Transformers are loaded from a file and applied in order
-    let migrations: Vec<Migration> = Asset::iter()
-        .sorted()
-        .filter(|filename| filename.ends_with(".yaml"))
-        .map(|filename| Asset::get(&filename).expect("migration must exist").data)
-        .map(|data| serde_yaml::from_slice(&data).expect("migration must be valid"))
+    upgrade_configuration_with_migrations(config, log_warnings, extra_migrations)
+        .map(|(config, _)| config)
+}
+
+/// Upgrades the configuration like [`upgrade_configuration`], and also returns the descriptions of
+/// the migrations that changed it, once each and in the order they were applied.
+pub(crate) fn upgrade_configuration_with_migrations(
+    config: &serde_json::Value,
+    log_warnings: bool,
+    extra_migrations: &[ConfigurationMigration],
+) -> Result<(serde_json::Value, Vec<String>), super::ConfigurationError> {
+    let (config, changes) =
+        upgrade_configuration_with_changes(config, log_warnings, extra_migrations)?;
+    let migrations = changes
+        .into_iter()
+        .map(|change| change.migration_description)
+        .unique()
         .collect();
+    Ok((config, migrations))
+}
+
+/// Upgrades the configuration like [`upgrade_configuration`], and also returns the changes that
+/// the migrations made to it, in the order they were made.
+fn upgrade_configuration_with_changes(
+    config: &serde_json::Value,
+    log_warnings: bool,
+    extra_migrations: &[ConfigurationMigration],
+) -> Result<(serde_json::Value, Vec<AppliedChange>), super::ConfigurationError> {
+    // Transformers are loaded from a file and applied in order
+    let migrations = load_migrations(extra_migrations)?;
 
     let mut config = config.clone();
 
     let mut effective_migrations = Vec::new();
+    let mut changes = Vec::new();
+    let mut errors = Vec::new();
     for migration in &migrations {
-        let new_config = apply_migration(&config, migration)?;
+        // A failed migration leaves the config as it was so that the remaining migrations can
+        // still be applied, and every error reported at once
+        let (new_config, migration_changes) = match apply_migration_with_changes(&config, migration)
+        {
+            Ok(applied) => applied,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
 
         // If the config has been modified by the migration then let the user know
         if new_config != config {
             effective_migrations.push(migration);
         }
+        changes.extend(migration_changes);
 
         // Get ready for the next migration
         config = new_config;
     }
+    if !errors.is_empty() {
+        return Err(combine_errors(errors));
+    }
     if !effective_migrations.is_empty() && log_warnings {
         tracing::warn!("router configuration contains deprecated options: \n\n{}\n\nThese will become errors in the future. Run `router config upgrade <path_to_router.yaml>` to see a suggested upgraded configuration.", effective_migrations.iter().enumerate().map(|(idx, m)|format!("  {}. {}", idx + 1, m.description)).join("\n\n"));
     }
-    Ok(config)
+    Ok((config, changes))
+}
+
+/// A type that [`Action::Coerce`] converts values to.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CoercionType {
+    /// A number, parsed from a string
+    Number,
+    /// A string, from a number or a boolean
+    String,
+    /// An array containing a single string, number or boolean
+    Array,
+}
+
+impl std::fmt::Display for CoercionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoercionType::Number => f.write_str("number"),
+            CoercionType::String => f.write_str("string"),
+            CoercionType::Array => f.write_str("array"),
+        }
+    }
+}
+
+/// A change made to the configuration by a migration.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct AppliedChange {
+    /// The description of the migration that made the change
+    pub(crate) migration_description: String,
+    /// A summary of the action that made the change, eg. `move a.b to c.b`
+    pub(crate) action_summary: String,
+    /// The JSON path of the changed value, eg. `$.c.b`
+    pub(crate) json_path: String,
+    /// The value at `json_path` before the change, if there was one
+    pub(crate) before: Option<Value>,
+    /// The value at `json_path` after the change, if there is one
+    pub(crate) after: Option<Value>,
+}
+
+/// Loads the router's own migrations, followed by the `extra` ones ordered by name.
+fn load_migrations(extra: &[ConfigurationMigration]) -> Result<Vec<Migration>, ConfigurationError> {
+    let (migrations, errors): (Vec<_>, Vec<_>) = Asset::iter()
+        .sorted()
+        .filter(|filename| filename.ends_with(".yaml"))
+        .map(|filename| {
+            let file =
+                Asset::get(&filename).ok_or_else(|| ConfigurationError::InvalidMigration {
+                    file: filename.to_string(),
+                    error: "migration does not exist".to_string(),
+                })?;
+            parse_migration(&filename, &file.data)
+        })
+        .partition_result();
+    if !errors.is_empty() {
+        return Err(combine_errors(errors));
+    }
+    let extra = extra.iter().sorted_by(|a, b| a.name.cmp(&b.name));
+    if let Some((duplicate, _)) = extra
+        .clone()
+        .tuple_windows()
+        .find(|(a, b)| a.name == b.name)
+    {
+        return Err(ConfigurationError::InvalidMigration {
+            file: duplicate.name.clone(),
+            error: "there are several migrations with this name".to_string(),
+        });
+    }
+    Ok(migrations
+        .into_iter()
+        .chain(extra.map(|extra| extra.migration.clone()))
+        .collect())
+}
+
+/// Turns the errors collected while upgrading into a single error, listing each of them if there
+/// are several.
+fn combine_errors(mut errors: Vec<ConfigurationError>) -> ConfigurationError {
+    if errors.len() == 1 {
+        return errors.remove(0);
+    }
+    ConfigurationError::InvalidConfiguration {
+        message: "configuration could not be upgraded",
+        error: format!(
+            "\n{}",
+            errors
+                .iter()
+                .enumerate()
+                .map(|(idx, e)| format!("{}. {e}", idx + 1))
+                .join("\n")
+        ),
+    }
+}
+
+fn parse_migration(file: &str, data: &[u8]) -> Result<Migration, ConfigurationError> {
+    let migration: Migration =
+        serde_yaml::from_slice(data).map_err(|e| ConfigurationError::InvalidMigration {
+            file: file.to_string(),
+            error: e.to_string(),
+        })?;
+    validate_actions(&migration.actions).map_err(|error| ConfigurationError::InvalidMigration {
+        file: file.to_string(),
+        error,
+    })?;
+    Ok(migration)
+}
+
+/// Checks what deserialization can't, so that applying the actions can't fail on it later.
+fn validate_actions(actions: &[Action]) -> Result<(), String> {
+    for action in actions {
+        match action {
+            Action::Add { path, name, .. } => validate_destination(&format!("{path}.{name}"))?,
+            Action::Append { path, .. }
+            | Action::Change { path, .. }
+            | Action::Coerce { path, .. } => validate_destination(path)?,
+            Action::Delete { path } => validate_deleted(path)?,
+            Action::Copy { from, to } | Action::Merge { from, to } => {
+                validate_source(from)?;
+                validate_destination(to)?;
+            }
+            Action::Move { from, to } => {
+                validate_source(from)?;
+                validate_deleted(from)?;
+                validate_destination(to)?;
+            }
+            Action::Log { level, .. } => {
+                Level::from_str(level).map_err(|_| format!("unknown log level '{level}'"))?;
+            }
+            Action::When { then, .. } => validate_actions(then)?,
+        }
+    }
+    Ok(())
+}
+
+/// Fills the wildcards of `path` with a placeholder key, as they would be once expanded against
+/// the configuration.
+fn placeholder_path(path: &str) -> String {
+    let captures = vec![PathSegment::Key("placeholder".to_string()); path.matches('*').count()];
+    fill_wildcards(path, &captures)
+}
+
+fn validate_source(path: &str) -> Result<(), String> {
+    Parser::parse(&placeholder_path(path), "")
+        .map(|_| ())
+        .map_err(|e| format!("invalid path '{path}': {e}"))
+}
+
+fn validate_destination(path: &str) -> Result<(), String> {
+    Parser::parse("", &placeholder_path(path))
+        .map(|_| ())
+        .map_err(|e| format!("invalid path '{path}': {e}"))
+}
+
+/// Deleted paths are removed without proteus, so they must be paths that [`parse_path`] accepts.
+fn validate_deleted(path: &str) -> Result<(), String> {
+    parse_path(path)
+        .map(|_| ())
+        .ok_or_else(|| format!("invalid path '{path}'"))
 }
 
-fn apply_migration(config: &Value, migration: &Migration) -> Result<Value, ConfigurationError> {
-    let mut transformer_builder = TransformBuilder::default();
+fn apply_migration_with_changes(
+    config: &Value,
+    migration: &Migration,
+) -> Result<(Value, Vec<AppliedChange>), ConfigurationError> {
     //We always copy the entire doc to the destination first
-    transformer_builder =
-        transformer_builder.add_action(Parser::parse("", "").expect("migration must be valid"));
+    let mut transformer_builder = add_transform(TransformBuilder::default(), "", "")?;
+    let mut touched = Vec::new();
+    let mut writes = Vec::new();
+    let mut deletions = Vec::new();
     for action in &migration.actions {
-        match action {
-            Action::Add { path, name, value } => {
-                if !jsonpath_lib::select(config, &format!("$.{path}"))
-                    .unwrap_or_default()
-                    .is_empty()
-                    && jsonpath_lib::select(config, &format!("$.{path}.{name}"))
-                        .unwrap_or_default()
-                        .is_empty()
-                {
-                    transformer_builder = transformer_builder.add_action(
-                        Parser::parse(&format!(r#"const({value})"#), &format!("{path}.{name}"))
-                            .expect("migration must be valid"),
-                    );
+        transformer_builder = add_action(
+            config,
+            action,
+            transformer_builder,
+            &mut touched,
+            &mut writes,
+            &mut deletions,
+        )?;
+    }
+    let transformer =
+        transformer_builder
+            .build()
+            .map_err(|e| ConfigurationError::MigrationFailure {
+                error: e.to_string(),
+            })?;
+    let mut new_config =
+        transformer
+            .apply(config)
+            .map_err(|e| ConfigurationError::MigrationFailure {
+                error: e.to_string(),
+            })?;
+
+    // Deleting isn't supported by proteus, so deleted paths are removed once it is done.
+    delete_paths(&mut new_config, &paths_to_delete(deletions, &writes)?);
+
+    // Keep the keys in the order the user wrote them, so that upgrade diffs stay small
+    preserve_key_order(config, &mut new_config);
+
+    let changes = touched
+        .into_iter()
+        .filter_map(
+            |TouchedPath {
+                 action_summary,
+                 path,
+             }| {
+                let before = select_first(config, &path).cloned();
+                let after = select_first(&new_config, &path).cloned();
+                (before != after).then(|| AppliedChange {
+                    migration_description: migration.description.clone(),
+                    action_summary,
+                    json_path: format!("$.{path}"),
+                    before,
+                    after,
+                })
+            },
+        )
+        .collect();
+
+    Ok((new_config, changes))
+}
+
+/// A path written to by a migration action, used to report the change once the migration is
+/// applied.
+struct TouchedPath {
+    action_summary: String,
+    path: String,
+}
+
+impl TouchedPath {
+    fn new(action_summary: String, path: String) -> Self {
+        Self {
+            action_summary,
+            path,
+        }
+    }
+}
+
+/// Adds the transforms for an action to the builder. Actions only look at the configuration as
+/// it was before the migration, so they are unaffected by the other actions of the migration.
+///
+/// The paths written to are added to `writes`. Paths to delete are added to `deletions` rather than
+/// to the builder, and are removed after all the transforms have been applied.
+fn add_action(
+    config: &Value,
+    action: &Action,
+    mut transformer_builder: TransformBuilder,
+    touched: &mut Vec<TouchedPath>,
+    writes: &mut Vec<String>,
+    deletions: &mut Vec<String>,
+) -> Result<TransformBuilder, ConfigurationError> {
+    match action {
+        Action::Add { path, name, value } => {
+            for (path, _) in expand_path(config, path) {
+                if path_exists(config, &path) && !path_exists(config, &format!("{path}.{name}")) {
+                    transformer_builder = add_transform(
+                        transformer_builder,
+                        &format!(r#"const({value})"#),
+                        &format!("{path}.{name}"),
+                    )?;
+                    writes.push(format!("{path}.{name}"));
+                    touched.push(TouchedPath::new(
+                        format!("add {name} to {path}"),
+                        format!("{path}.{name}"),
+                    ));
                 }
             }
-            Action::Delete { path } => {
-                if !jsonpath_lib::select(config, &format!("$.{path}"))
-                    .unwrap_or_default()
-                    .is_empty()
-                {
-                    // Deleting isn't actually supported by protus so we add a magic value to delete later
-                    transformer_builder = transformer_builder.add_action(
-                        Parser::parse(REMOVAL_EXPRESSION, path).expect("migration must be valid"),
-                    );
+        }
+        Action::Append {
+            path,
+            value,
+            skip_if_present,
+        } => {
+            for (path, _) in expand_path(config, path) {
+                let Some(Value::Array(array)) = select_first(config, &path) else {
+                    continue;
+                };
+                if *skip_if_present && array.contains(value) {
+                    continue;
                 }
+                let mut appended = array.clone();
+                appended.push(value.clone());
+                let appended = Value::Array(appended);
+                transformer_builder =
+                    add_transform(transformer_builder, &format!(r#"const({appended})"#), &path)?;
+                writes.push(path.clone());
+                touched.push(TouchedPath::new(format!("append {value} to {path}"), path));
             }
-            Action::Copy { from, to } => {
-                if !jsonpath_lib::select(config, &format!("$.{from}"))
-                    .unwrap_or_default()
-                    .is_empty()
-                {
-                    transformer_builder = transformer_builder
-                        .add_action(Parser::parse(from, to).expect("migration must be valid"));
+        }
+        Action::Delete { path } => {
+            for (path, _) in expand_path(config, path) {
+                if path_exists(config, &path) {
+                    deletions.push(path.clone());
+                    touched.push(TouchedPath::new(format!("delete {path}"), path));
                 }
             }
-            Action::Move { from, to } => {
-                if !jsonpath_lib::select(config, &format!("$.{from}"))
-                    .unwrap_or_default()
-                    .is_empty()
-                {
-                    transformer_builder = transformer_builder
-                        .add_action(Parser::parse(from, to).expect("migration must be valid"));
-                    // Deleting isn't actually supported by protus so we add a magic value to delete later
-                    transformer_builder = transformer_builder.add_action(
-                        Parser::parse(REMOVAL_EXPRESSION, from).expect("migration must be valid"),
-                    );
+        }
+        Action::Copy { from, to } => {
+            // Each match of a wildcard path is copied to its own destination
+            for (from, captures) in expand_path(config, from) {
+                if !path_exists(config, &from) {
+                    continue;
+                }
+                let to = fill_wildcards(to, &captures);
+                transformer_builder = add_transform(transformer_builder, &from, &to)?;
+                writes.push(to.clone());
+                touched.push(TouchedPath::new(format!("copy {from} to {to}"), to));
+            }
+        }
+        Action::Move { from, to } => {
+            // Each match of a wildcard path is moved to its own destination
+            for (from, captures) in expand_path(config, from) {
+                if !path_exists(config, &from) {
+                    continue;
+                }
+                let to = fill_wildcards(to, &captures);
+                transformer_builder = add_transform(transformer_builder, &from, &to)?;
+                writes.push(to.clone());
+                deletions.push(from.clone());
+                let action_summary = format!("move {from} to {to}");
+                touched.push(TouchedPath::new(action_summary.clone(), from));
+                touched.push(TouchedPath::new(action_summary, to));
+            }
+        }
+        Action::Merge { from, to } => {
+            for (from, captures) in expand_path(config, from) {
+                let Some(source) = select_first(config, &from) else {
+                    continue;
+                };
+                let to = fill_wildcards(to, &captures);
+                let mut merged = select_first(config, &to).cloned().unwrap_or(Value::Null);
+                deep_merge(&mut merged, source);
+                transformer_builder =
+                    add_transform(transformer_builder, &format!(r#"const({merged})"#), &to)?;
+                writes.push(to.clone());
+                touched.push(TouchedPath::new(format!("merge {from} into {to}"), to));
+            }
+        }
+        Action::Coerce { path, to_type } => {
+            for (path, _) in expand_path(config, path) {
+                let Some(value) = select_first(config, &path) else {
+                    continue;
+                };
+                let coerced = coerce(value, *to_type).ok_or_else(|| {
+                    ConfigurationError::MigrationFailure {
+                        error: format!("cannot convert {value} at {path} to {to_type}"),
+                    }
+                })?;
+                if &coerced != value {
+                    transformer_builder =
+                        add_transform(transformer_builder, &format!(r#"const({coerced})"#), &path)?;
+                    writes.push(path.clone());
+                    touched.push(TouchedPath::new(
+                        format!("convert {path} to {to_type}"),
+                        path,
+                    ));
                 }
             }
-            Action::Change { path, from, to } => {
+        }
+        Action::Change { path, from, to } => {
+            for (path, _) in expand_path(config, path) {
                 if !jsonpath_lib::select(config, &format!("$[?(@.{path} == {from})]"))
                     .unwrap_or_default()
                     .is_empty()
                 {
-                    transformer_builder = transformer_builder.add_action(
-                        Parser::parse(&format!(r#"const({to})"#), path)
-                            .expect("migration must be valid"),
-                    );
+                    transformer_builder =
+                        add_transform(transformer_builder, &format!(r#"const({to})"#), &path)?;
+                    writes.push(path.clone());
+                    touched.push(TouchedPath::new(
+                        format!("change {path} from {from} to {to}"),
+                        path,
+                    ));
                 }
             }
-            Action::Log { path, level, log } => {
-                let level = Level::from_str(level).expect("unknown level for log migration");
+        }
+        Action::Log { path, level, log } => {
+            let level =
+                Level::from_str(level).map_err(|_| ConfigurationError::MigrationFailure {
+                    error: format!("unknown log level '{level}'"),
+                })?;
 
-                if !jsonpath_lib::select(config, &format!("$.{path}"))
-                    .unwrap_or_default()
-                    .is_empty()
-                {
-                    match level {
-                        Level::INFO => tracing::info!("{log}"),
-                        Level::ERROR => tracing::error!("{log}"),
-                        Level::WARN => tracing::warn!("{log}"),
-                        Level::TRACE => tracing::trace!("{log}"),
-                        Level::DEBUG => tracing::debug!("{log}"),
+            if expand_path(config, path)
+                .iter()
+                .any(|(path, _)| path_exists(config, path))
+            {
+                match level {
+                    Level::INFO => tracing::info!("{log}"),
+                    Level::ERROR => tracing::error!("{log}"),
+                    Level::WARN => tracing::warn!("{log}"),
+                    Level::TRACE => tracing::trace!("{log}"),
+                    Level::DEBUG => tracing::debug!("{log}"),
+                }
+            }
+        }
+        Action::When { path, equals, then } => {
+            if expand_path(config, path)
+                .iter()
+                .any(|(path, _)| select_first(config, path) == Some(equals))
+            {
+                for action in then {
+                    transformer_builder = add_action(
+                        config,
+                        action,
+                        transformer_builder,
+                        touched,
+                        writes,
+                        deletions,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(transformer_builder)
+}
+
+/// Adds the transform that sets `destination` from `source` to the builder. Paths filled in from
+/// the configuration, such as the matches of wildcards, may not be valid, so this fails the
+/// migration rather than panicking.
+fn add_transform(
+    transformer_builder: TransformBuilder,
+    source: &str,
+    destination: &str,
+) -> Result<TransformBuilder, ConfigurationError> {
+    let action =
+        Parser::parse(source, destination).map_err(|e| ConfigurationError::MigrationFailure {
+            error: format!("cannot set '{destination}' from '{source}': {e}"),
+        })?;
+    Ok(transformer_builder.add_action(action))
+}
+
+/// Converts `value` to `to_type`, returning `None` if it can't be converted.
+fn coerce(value: &Value, to_type: CoercionType) -> Option<Value> {
+    match (to_type, value) {
+        (CoercionType::Number, Value::Number(_))
+        | (CoercionType::String, Value::String(_))
+        | (CoercionType::Array, Value::Array(_)) => Some(value.clone()),
+        (CoercionType::Number, Value::String(string)) => string
+            .trim()
+            .parse::<serde_json::Number>()
+            .ok()
+            .map(Value::Number),
+        (CoercionType::String, Value::Number(number)) => Some(Value::String(number.to_string())),
+        (CoercionType::String, Value::Bool(bool)) => Some(Value::String(bool.to_string())),
+        (CoercionType::Array, Value::String(_) | Value::Number(_) | Value::Bool(_)) => {
+            Some(Value::Array(vec![value.clone()]))
+        }
+        _ => None,
+    }
+}
+
+fn path_exists(config: &Value, path: &str) -> bool {
+    !jsonpath_lib::select(config, &format!("$.{path}"))
+        .unwrap_or_default()
+        .is_empty()
+}
+
+fn select_first<'a>(config: &'a Value, path: &str) -> Option<&'a Value> {
+    jsonpath_lib::select(config, &format!("$.{path}"))
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+}
+
+/// Merges `source` into `destination`, recursing into objects present on both sides. Any other
+/// value of `source` replaces the one in `destination`.
+fn deep_merge(destination: &mut Value, source: &Value) {
+    match (destination, source) {
+        (Value::Object(destination), Value::Object(source)) => {
+            for (key, value) in source {
+                match destination.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        destination.insert(key.clone(), value.clone());
                     }
                 }
             }
         }
+        (destination, source) => *destination = source.clone(),
     }
-    let transformer = transformer_builder
-        .build()
-        .expect("transformer for migration must be valid");
-    let mut new_config =
-        transformer
-            .apply(config)
-            .map_err(|e| ConfigurationError::MigrationFailure {
-                error: e.to_string(),
-            })?;
+}
+
+/// A segment of a migration path. Object keys are separated by dots, or written as `["key"]` when
+/// they contain dots, and array indexes are written as `[0]`. A `*` (or `[*]`) segment is a
+/// wildcard that matches every key of an object or every element of an array, and a `**` segment
+/// is a recursive wildcard that matches any number of nested keys and indexes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+    RecursiveWildcard,
+    /// The keys and indexes matched by a recursive wildcard
+    Descendants(Vec<PathSegment>),
+}
 
-    // Now we need to clean up elements that should be deleted.
-    cleanup(&mut new_config);
+/// How deep a recursive wildcard descends into the configuration before giving up.
+const MAX_RECURSIVE_WILDCARD_DEPTH: usize = 32;
 
-    Ok(new_config)
+/// Splits a migration path into its names and its bracketed parts, eg. `a["b.c"][0].*` into `a`,
+/// `["b.c"]`, `[0]` and `*`.
+fn split_path(mut path: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    while !path.is_empty() {
+        let end = if let Some(quoted) = path.strip_prefix("[\"") {
+            quoted.find("\"]").map_or(path.len(), |end| end + 4)
+        } else if path.starts_with('[') {
+            path.find(']').map_or(path.len(), |end| end + 1)
+        } else {
+            path.find(|c: char| c == '.' || c == '[')
+                .unwrap_or(path.len())
+        };
+        let (part, rest) = path.split_at(end);
+        if !part.is_empty() {
+            parts.push(part);
+        }
+        path = rest.strip_prefix('.').unwrap_or(rest);
+    }
+    parts
 }
 
-pub(crate) fn generate_upgrade(config: &str, diff: bool) -> Result<String, ConfigurationError> {
-    let parsed_config =
-        serde_yaml::from_str(config).map_err(|e| ConfigurationError::MigrationFailure {
-            error: e.to_string(),
-        })?;
-    let upgraded_config = upgrade_configuration(&parsed_config, true).map_err(|e| {
-        ConfigurationError::MigrationFailure {
-            error: e.to_string(),
+/// Parses a path made of keys, indexes and wildcards. Returns `None` if a bracket does not contain
+/// an index, a quoted key or a wildcard.
+fn parse_path(path: &str) -> Option<Vec<PathSegment>> {
+    split_path(path)
+        .into_iter()
+        .map(|part| match part {
+            "*" | "[*]" => Some(PathSegment::Wildcard),
+            "**" => Some(PathSegment::RecursiveWildcard),
+            _ if part.starts_with('[') => {
+                let bracketed = part.strip_prefix('[')?.strip_suffix(']')?;
+                match bracketed
+                    .strip_prefix('"')
+                    .and_then(|key| key.strip_suffix('"'))
+                {
+                    Some(key) => Some(PathSegment::Key(key.to_string())),
+                    None => bracketed.parse().ok().map(PathSegment::Index),
+                }
+            }
+            _ => Some(PathSegment::Key(part.to_string())),
+        })
+        .collect()
+}
+
+fn push_segment(path: &mut String, segment: &PathSegment) {
+    match segment {
+        PathSegment::Key(key) if key.contains(|c: char| matches!(c, '.' | '[' | ']' | '*')) => {
+            write!(path, "[\"{key}\"]").expect("write will never fail");
         }
-    })?;
+        PathSegment::Key(key) => {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(key);
+        }
+        PathSegment::Index(index) => {
+            write!(path, "[{index}]").expect("write will never fail");
+        }
+        PathSegment::Wildcard => {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push('*');
+        }
+        PathSegment::RecursiveWildcard => {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str("**");
+        }
+        PathSegment::Descendants(segments) => {
+            for segment in segments {
+                push_segment(path, segment);
+            }
+        }
+    }
+}
+
+/// Expands the wildcards of `path` against `config`. Returns every concrete path that the
+/// wildcards match, along with the keys or indexes matched by each wildcard, in order.
+///
+/// Paths without wildcards are returned as they are, whether or not they exist in the config.
+fn expand_path(config: &Value, path: &str) -> Vec<(String, Vec<PathSegment>)> {
+    if !path.contains('*') {
+        return vec![(path.to_string(), Vec::new())];
+    }
+    let Some(segments) = parse_path(path) else {
+        return Vec::new();
+    };
+    let mut matches = Vec::new();
+    expand_segments(
+        config,
+        &segments,
+        &mut String::new(),
+        &mut Vec::new(),
+        &mut matches,
+    );
+    matches
+}
+
+fn expand_segments(
+    value: &Value,
+    segments: &[PathSegment],
+    path: &mut String,
+    captures: &mut Vec<PathSegment>,
+    matches: &mut Vec<(String, Vec<PathSegment>)>,
+) {
+    let Some((segment, rest)) = segments.split_first() else {
+        matches.push((path.clone(), captures.clone()));
+        return;
+    };
+    let mut descend = |child: &Value, segment: PathSegment, captured: bool| {
+        let len = path.len();
+        push_segment(path, &segment);
+        if captured {
+            captures.push(segment);
+        }
+        expand_segments(child, rest, path, captures, matches);
+        if captured {
+            captures.pop();
+        }
+        path.truncate(len);
+    };
+    match (segment, value) {
+        (PathSegment::Key(key), Value::Object(object)) => {
+            if let Some(child) = object.get(key) {
+                descend(child, segment.clone(), false);
+            }
+        }
+        (PathSegment::Index(index), Value::Array(array)) => {
+            if let Some(child) = array.get(*index) {
+                descend(child, segment.clone(), false);
+            }
+        }
+        (PathSegment::Wildcard, Value::Object(object)) => {
+            for (key, child) in object {
+                descend(child, PathSegment::Key(key.clone()), true);
+            }
+        }
+        (PathSegment::Wildcard, Value::Array(array)) => {
+            for (index, child) in array.iter().enumerate() {
+                descend(child, PathSegment::Index(index), true);
+            }
+        }
+        (PathSegment::RecursiveWildcard, _) => {
+            expand_descendants(value, rest, path, captures, matches, &mut Vec::new());
+        }
+        _ => {}
+    }
+}
+
+/// Expands a recursive wildcard by descending into `value` until the rest of the path matches.
+///
+/// This visits every value below the wildcard that the rest of the path doesn't match, so it is
+/// best followed by a key that narrows the matches down, and kept under a specific part of the
+/// configuration rather than at its root. Values nested deeper than
+/// [`MAX_RECURSIVE_WILDCARD_DEPTH`] below the wildcard are never matched.
+fn expand_descendants(
+    value: &Value,
+    rest: &[PathSegment],
+    path: &mut String,
+    captures: &mut Vec<PathSegment>,
+    matches: &mut Vec<(String, Vec<PathSegment>)>,
+    descendants: &mut Vec<PathSegment>,
+) {
+    let matched = matches.len();
+    captures.push(PathSegment::Descendants(descendants.clone()));
+    expand_segments(value, rest, path, captures, matches);
+    captures.pop();
+    if matches.len() > matched || descendants.len() >= MAX_RECURSIVE_WILDCARD_DEPTH {
+        return;
+    }
+
+    let children: Vec<(PathSegment, &Value)> = match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| (PathSegment::Key(key.clone()), child))
+            .collect(),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, child)| (PathSegment::Index(index), child))
+            .collect(),
+        _ => return,
+    };
+    for (segment, child) in children {
+        let len = path.len();
+        push_segment(path, &segment);
+        descendants.push(segment);
+        expand_descendants(child, rest, path, captures, matches, descendants);
+        descendants.pop();
+        path.truncate(len);
+    }
+}
+
+/// Replaces the wildcards of a destination path with the keys or indexes matched by the wildcards
+/// of the source path, in order. Any other part of the destination, including setter syntax such
+/// as `[]`, is kept as it is.
+fn fill_wildcards(template: &str, captures: &[PathSegment]) -> String {
+    if captures.is_empty() {
+        return template.to_string();
+    }
+    let mut captures = captures.iter();
+    let mut filled = String::new();
+    let mut fill = |filled: &mut String| match captures.next() {
+        Some(capture) => push_segment(filled, capture),
+        None => push_segment(filled, &PathSegment::Wildcard),
+    };
+    for part in split_path(template) {
+        match part {
+            "*" | "**" | "[*]" => fill(&mut filled),
+            _ if part.starts_with('[') => filled.push_str(part),
+            _ => push_segment(&mut filled, &PathSegment::Key(part.to_string())),
+        }
+    }
+    filled
+}
+
+pub(crate) fn generate_upgrade(
+    config: &str,
+    diff: bool,
+    extra_migrations: &[ConfigurationMigration],
+) -> Result<String, ConfigurationError> {
+    let (upgraded_config, changes) = upgrade_yaml(config, true, extra_migrations)?;
+    if let Some(edited_config) = document::upgrade_document(config, &changes, &upgraded_config) {
+        return Ok(generate_edited_upgrade_output(config, &edited_config, diff));
+    }
+    // The configuration could not be edited in place, so the upgraded configuration is written
+    // out as a whole instead
     let upgraded_config = serde_yaml::to_string(&upgraded_config).map_err(|e| {
         ConfigurationError::MigrationFailure {
             error: e.to_string(),
@@ -206,21 +915,53 @@ pub(crate) fn generate_upgrade(config: &str, diff: bool) -> Result<String, Confi
     generate_upgrade_output(config, &upgraded_config, diff)
 }
 
+/// Lists the changes that upgrading the configuration makes to it, as JSON, for tooling to review
+/// them.
+pub(crate) fn generate_upgrade_changes(
+    config: &str,
+    extra_migrations: &[ConfigurationMigration],
+) -> Result<String, ConfigurationError> {
+    let (_, changes) = upgrade_yaml(config, false, extra_migrations)?;
+    serde_json::to_string_pretty(&changes).map_err(|e| ConfigurationError::MigrationFailure {
+        error: e.to_string(),
+    })
+}
+
+fn upgrade_yaml(
+    config: &str,
+    log_warnings: bool,
+    extra_migrations: &[ConfigurationMigration],
+) -> Result<(Value, Vec<AppliedChange>), ConfigurationError> {
+    let parsed_config =
+        serde_yaml::from_str(config).map_err(|e| ConfigurationError::MigrationFailure {
+            error: e.to_string(),
+        })?;
+    upgrade_configuration_with_changes(&parsed_config, log_warnings, extra_migrations).map_err(
+        |e| ConfigurationError::MigrationFailure {
+            error: e.to_string(),
+        },
+    )
+}
+
 pub(crate) fn generate_upgrade_output(
     config: &str,
     upgraded_config: &str,
     diff: bool,
 ) -> Result<String, ConfigurationError> {
-    // serde doesn't deal with whitespace and comments, these are lost in the upgrade process, so instead we try and preserve this in the diff.
-    // It's not ideal, and ideally the upgrade process should work on a DOM that is not serde, but for now we just make a best effort to preserve comments and whitespace.
+    // serde doesn't deal with whitespace and comments, these are lost when the upgraded configuration is written out as a whole, so instead we try and preserve this in the diff.
+    // This is only used when the configuration cannot be edited in place, so we just make a best effort to preserve comments and whitespace.
+    // Lines are compared without their inline comments, so that a line that was not changed by the upgrade keeps its comment.
     // There absolutely are issues where comments will get stripped, but the output should be `correct`.
     let mut output = String::new();
 
-    let diff_result = diff::lines(config, upgraded_config);
+    let config_lines: Vec<ConfigLine> = config.lines().map(ConfigLine::new).collect();
+    let upgraded_config_lines: Vec<ConfigLine> =
+        upgraded_config.lines().map(ConfigLine::new).collect();
+    let diff_result = diff::slice(&config_lines, &upgraded_config_lines);
 
     for diff_line in diff_result {
         match diff_line {
-            diff::Result::Left(l) => {
+            diff::Result::Left(ConfigLine { line: l, .. }) => {
                 let trimmed = l.trim();
                 if !trimmed.starts_with('#') && !trimmed.is_empty() {
                     if diff {
@@ -232,14 +973,14 @@ pub(crate) fn generate_upgrade_output(
                     writeln!(output, "{l}").expect("write will never fail");
                 }
             }
-            diff::Result::Both(l, _) => {
+            diff::Result::Both(ConfigLine { line: l, .. }, _) => {
                 if diff {
                     writeln!(output, " {l}").expect("write will never fail");
                 } else {
                     writeln!(output, "{l}").expect("write will never fail");
                 }
             }
-            diff::Result::Right(r) => {
+            diff::Result::Right(ConfigLine { line: r, .. }) => {
                 let trimmed = r.trim();
                 if trimmed != "---" && !trimmed.is_empty() {
                     if diff {
@@ -254,38 +995,200 @@ pub(crate) fn generate_upgrade_output(
     Ok(output)
 }
 
-fn cleanup(value: &mut Value) {
-    match value {
-        Value::Null => {}
-        Value::Bool(_) => {}
-        Value::Number(_) => {}
-        Value::String(_) => {}
-        Value::Array(a) => {
-            a.retain(|v| &Value::String(REMOVAL_VALUE.to_string()) != v);
-            for value in a {
-                cleanup(value);
-            }
+/// Outputs the configuration edited in place by the upgrade, or the lines that the upgrade changed
+/// in it.
+fn generate_edited_upgrade_output(config: &str, edited_config: &str, diff: bool) -> String {
+    if !diff {
+        return edited_config.to_string();
+    }
+    let mut output = String::new();
+    let config_lines: Vec<&str> = config.lines().collect();
+    let edited_config_lines: Vec<&str> = edited_config.lines().collect();
+    for diff_line in diff::slice(&config_lines, &edited_config_lines) {
+        match diff_line {
+            diff::Result::Left(l) => writeln!(output, "-{l}"),
+            diff::Result::Both(l, _) => writeln!(output, " {l}"),
+            diff::Result::Right(r) => writeln!(output, "+{r}"),
         }
-        Value::Object(o) => {
-            o.retain(|_, v| &Value::String(REMOVAL_VALUE.to_string()) != v);
-            for value in o.values_mut() {
-                cleanup(value);
+        .expect("write will never fail");
+    }
+    output
+}
+
+/// A line of YAML that compares equal to another line if they only differ by an inline comment or
+/// trailing whitespace.
+struct ConfigLine<'a> {
+    line: &'a str,
+    without_comment: &'a str,
+}
+
+impl<'a> ConfigLine<'a> {
+    fn new(line: &'a str) -> Self {
+        let mut quote = None;
+        let mut previous = ' ';
+        let mut end = line.len();
+        for (index, c) in line.char_indices() {
+            match (quote, c) {
+                (None, '\'' | '"') => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, '#') if previous.is_whitespace() => {
+                    end = index;
+                    break;
+                }
+                _ => {}
             }
+            previous = c;
+        }
+        Self {
+            line,
+            without_comment: line[..end].trim_end(),
         }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use serde_json::json;
-    use serde_json::Value;
+impl PartialEq for ConfigLine<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.without_comment == other.without_comment
+    }
+}
 
-    use crate::configuration::upgrade::apply_migration;
-    use crate::configuration::upgrade::generate_upgrade_output;
-    use crate::configuration::upgrade::Action;
-    use crate::configuration::upgrade::Migration;
+/// Reorders the keys of the objects in `value` to match `original`. Keys that were already in
+/// `original` keep their order, and the keys added to an object come after them.
+fn preserve_key_order(original: &Value, value: &mut Value) {
+    match (original, value) {
+        (Value::Object(original), Value::Object(object)) => {
+            let positions: HashMap<&str, usize> = original
+                .keys()
+                .enumerate()
+                .map(|(position, key)| (key.as_str(), position))
+                .collect();
+            let mut entries: Vec<(String, Value)> = std::mem::take(object).into_iter().collect();
+            // The sort is stable, so added keys stay in the order they were added in
+            entries
+                .sort_by_key(|(key, _)| positions.get(key.as_str()).copied().unwrap_or(usize::MAX));
+            for (key, value) in &mut entries {
+                if let Some(original) = original.get(key) {
+                    preserve_key_order(original, value);
+                }
+            }
+            *object = entries.into_iter().collect();
+        }
+        (Value::Array(original), Value::Array(array)) => {
+            for (original, value) in original.iter().zip(array) {
+                preserve_key_order(original, value);
+            }
+        }
+        _ => {}
+    }
+}
 
-    fn source_doc() -> Value {
+/// Returns the paths to delete once the transforms of a migration have been applied. Actions only
+/// look at the configuration as it was before the migration, so deleting a path removes the value
+/// that it had then: a path is not deleted if the migration also writes to it, or to a path that
+/// contains it, whatever the order of the actions. Writing within a deleted path is rejected, as
+/// the value written would be removed along with the path.
+fn paths_to_delete(
+    deletions: Vec<String>,
+    writes: &[String],
+) -> Result<Vec<String>, ConfigurationError> {
+    let writes: Vec<(&String, Vec<PathSegment>)> = writes
+        .iter()
+        .filter_map(|write| Some((write, parse_path(write)?)))
+        .collect();
+    let mut paths = Vec::new();
+    for deletion in deletions {
+        let Some(deleted) = parse_path(&deletion) else {
+            continue;
+        };
+        if writes
+            .iter()
+            .any(|(_, written)| deleted.starts_with(written))
+        {
+            continue;
+        }
+        if let Some((write, _)) = writes
+            .iter()
+            .find(|(_, written)| written.starts_with(&deleted))
+        {
+            return Err(ConfigurationError::MigrationFailure {
+                error: format!("cannot write to {write}, as the same migration deletes {deletion}"),
+            });
+        }
+        paths.push(deletion);
+    }
+    Ok(paths)
+}
+
+/// Removes each of `paths` from `value`. Paths that do not exist are ignored.
+fn delete_paths(value: &mut Value, paths: &[String]) {
+    let mut paths: Vec<Vec<PathSegment>> = paths.iter().filter_map(|p| parse_path(p)).collect();
+    // Removing an array element shifts the ones after it, so the highest indexes are removed first
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    paths.dedup();
+    for path in paths {
+        delete_path(value, &path);
+    }
+}
+
+fn delete_path(value: &mut Value, path: &[PathSegment]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut parent = value;
+    for segment in parents {
+        let child = match (segment, parent) {
+            (PathSegment::Key(key), Value::Object(object)) => object.get_mut(key),
+            (PathSegment::Index(index), Value::Array(array)) => array.get_mut(*index),
+            _ => None,
+        };
+        let Some(child) = child else {
+            return;
+        };
+        parent = child;
+    }
+    match (last, parent) {
+        (PathSegment::Key(key), Value::Object(object)) => {
+            // Unlike `remove`, `retain` keeps the order of the remaining keys
+            object.retain(|k, _| k != key);
+        }
+        (PathSegment::Index(index), Value::Array(array)) if *index < array.len() => {
+            array.remove(*index);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use serde_json::Value;
+
+    use crate::configuration::upgrade::apply_migration_with_changes;
+    use crate::configuration::upgrade::combine_errors;
+    use crate::configuration::upgrade::expand_path;
+    use crate::configuration::upgrade::fill_wildcards;
+    use crate::configuration::upgrade::generate_upgrade;
+    use crate::configuration::upgrade::generate_upgrade_changes;
+    use crate::configuration::upgrade::generate_upgrade_output;
+    use crate::configuration::upgrade::load_migrations;
+    use crate::configuration::upgrade::parse_migration;
+    use crate::configuration::upgrade::parse_path;
+    use crate::configuration::upgrade::preserve_key_order;
+    use crate::configuration::upgrade::upgrade_configuration_with_changes;
+    use crate::configuration::upgrade::upgrade_configuration_with_migrations;
+    use crate::configuration::upgrade::Action;
+    use crate::configuration::upgrade::CoercionType;
+    use crate::configuration::upgrade::ConfigurationMigration;
+    use crate::configuration::upgrade::Migration;
+    use crate::configuration::upgrade::PathSegment;
+    use crate::configuration::upgrade::MAX_RECURSIVE_WILDCARD_DEPTH;
+    use crate::error::ConfigurationError;
+
+    fn apply_migration(config: &Value, migration: &Migration) -> Result<Value, ConfigurationError> {
+        apply_migration_with_changes(config, migration).map(|(new_config, _)| new_config)
+    }
+
+    fn source_doc() -> Value {
         json!( {
           "obj": {
                 "field1": 1,
@@ -298,6 +1201,29 @@ mod test {
         })
     }
 
+    fn wildcard_doc() -> Value {
+        json!({
+          "subgraphs": {
+                "products": {
+                    "timeout": "1s",
+                    "retries": 1
+                },
+                "reviews": {
+                    "timeout": "2s"
+                }
+            },
+          "arr": [
+                {
+                    "a": 1,
+                    "b": 1
+                },
+                {
+                    "a": 2
+                }
+            ]
+        })
+    }
+
     #[test]
     fn delete_field() {
         insta::assert_json_snapshot!(apply_migration(
@@ -480,4 +1406,857 @@ mod test {
         )
         .expect("expected successful migration"));
     }
+
+    #[test]
+    fn expand_wildcard_path() {
+        assert_eq!(
+            expand_path(&wildcard_doc(), "subgraphs.*.timeout"),
+            vec![
+                (
+                    "subgraphs.products.timeout".to_string(),
+                    vec![PathSegment::Key("products".to_string())]
+                ),
+                (
+                    "subgraphs.reviews.timeout".to_string(),
+                    vec![PathSegment::Key("reviews".to_string())]
+                ),
+            ]
+        );
+        assert_eq!(
+            expand_path(&wildcard_doc(), "arr[*].a"),
+            vec![
+                ("arr[0].a".to_string(), vec![PathSegment::Index(0)]),
+                ("arr[1].a".to_string(), vec![PathSegment::Index(1)]),
+            ]
+        );
+        assert_eq!(
+            expand_path(&wildcard_doc(), "arr.*.b"),
+            vec![("arr[0].b".to_string(), vec![PathSegment::Index(0)])]
+        );
+        assert!(expand_path(&wildcard_doc(), "missing.*").is_empty());
+        assert_eq!(
+            expand_path(&wildcard_doc(), "missing.field"),
+            vec![("missing.field".to_string(), vec![])]
+        );
+    }
+
+    #[test]
+    fn expand_recursive_wildcard_path() {
+        let descendants = |keys: &[&str]| {
+            vec![PathSegment::Descendants(
+                keys.iter()
+                    .map(|key| PathSegment::Key(key.to_string()))
+                    .collect(),
+            )]
+        };
+        assert_eq!(
+            expand_path(&wildcard_doc(), "**.timeout"),
+            vec![
+                (
+                    "subgraphs.products.timeout".to_string(),
+                    descendants(&["subgraphs", "products"])
+                ),
+                (
+                    "subgraphs.reviews.timeout".to_string(),
+                    descendants(&["subgraphs", "reviews"])
+                ),
+            ]
+        );
+        assert_eq!(
+            expand_path(&wildcard_doc(), "subgraphs.**.retries"),
+            vec![(
+                "subgraphs.products.retries".to_string(),
+                descendants(&["products"])
+            )]
+        );
+        assert_eq!(
+            expand_path(&wildcard_doc(), "**.b"),
+            vec![(
+                "arr[0].b".to_string(),
+                vec![PathSegment::Descendants(vec![
+                    PathSegment::Key("arr".to_string()),
+                    PathSegment::Index(0)
+                ])]
+            )]
+        );
+    }
+
+    #[test]
+    fn recursive_wildcard_depth_is_capped() {
+        let mut config = json!({ "target": true });
+        for _ in 0..=MAX_RECURSIVE_WILDCARD_DEPTH {
+            config = json!({ "nested": config });
+        }
+        assert_eq!(expand_path(&config, "nested.**.target").len(), 1);
+        assert!(expand_path(&config, "**.target").is_empty());
+    }
+
+    #[test]
+    fn move_recursive_wildcard_field() {
+        let new_config = apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Move {
+                    from: "**.timeout".to_string(),
+                    to: "**.http.timeout".to_string(),
+                })
+                .description("move timeouts")
+                .build(),
+        )
+        .expect("expected successful migration");
+        assert_eq!(
+            new_config["subgraphs"],
+            json!({
+                "products": {
+                    "retries": 1,
+                    "http": {
+                        "timeout": "1s"
+                    }
+                },
+                "reviews": {
+                    "http": {
+                        "timeout": "2s"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn fill_wildcard_path() {
+        assert_eq!(
+            fill_wildcards(
+                "new.*.http.timeout",
+                &[PathSegment::Key("products".to_string())]
+            ),
+            "new.products.http.timeout"
+        );
+        assert_eq!(
+            fill_wildcards("new.*.a", &[PathSegment::Index(1)]),
+            "new[1].a"
+        );
+        assert_eq!(
+            fill_wildcards("new[*].list[]", &[PathSegment::Index(1)]),
+            "new[1].list[]"
+        );
+        assert_eq!(
+            fill_wildcards(r#"new["a.b"].*"#, &[PathSegment::Key("c.d".to_string())]),
+            r#"new["a.b"]["c.d"]"#
+        );
+    }
+
+    #[test]
+    fn parse_quoted_path() {
+        assert_eq!(
+            parse_path(r#"a["b.c"][0].*"#),
+            Some(vec![
+                PathSegment::Key("a".to_string()),
+                PathSegment::Key("b.c".to_string()),
+                PathSegment::Index(0),
+                PathSegment::Wildcard,
+            ])
+        );
+        assert_eq!(parse_path("a[b]"), None);
+        assert_eq!(parse_path(r#"a["b"#), None);
+    }
+
+    #[test]
+    fn move_quoted_field() {
+        assert_eq!(
+            apply_migration(
+                &json!({"attributes": {"entity.type": true}}),
+                &Migration::builder()
+                    .action(Action::Move {
+                        from: r#"attributes["entity.type"]"#.to_string(),
+                        to: r#"attributes["graphql.type.name"]"#.to_string()
+                    })
+                    .description("move entity.type")
+                    .build(),
+            )
+            .expect("expected successful migration"),
+            json!({"attributes": {"graphql.type.name": true}})
+        );
+    }
+
+    #[test]
+    fn move_chain() {
+        // Moving `b` away doesn't delete the value that `a` is moved to
+        assert_eq!(
+            apply_migration(
+                &json!({"a": 1, "b": 2}),
+                &Migration::builder()
+                    .action(Action::Move {
+                        from: "a".to_string(),
+                        to: "b".to_string()
+                    })
+                    .action(Action::Move {
+                        from: "b".to_string(),
+                        to: "c".to_string()
+                    })
+                    .description("shift a to b and b to c")
+                    .build(),
+            )
+            .expect("expected successful migration"),
+            json!({"b": 1, "c": 2})
+        );
+    }
+
+    #[test]
+    fn write_within_deleted_path() {
+        let error = apply_migration(
+            &json!({"a": {"b": 1}, "c": 2}),
+            &Migration::builder()
+                .action(Action::Delete {
+                    path: "a".to_string(),
+                })
+                .action(Action::Copy {
+                    from: "c".to_string(),
+                    to: "a.c".to_string(),
+                })
+                .description("delete a and copy c into it")
+                .build(),
+        )
+        .expect_err("expected failed migration");
+        assert_eq!(
+            error.to_string(),
+            "could not migrate configuration: cannot write to a.c, as the same migration deletes a."
+        );
+    }
+
+    #[test]
+    fn delete_wildcard_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Delete {
+                    path: "subgraphs.*.timeout".to_string()
+                })
+                .description("delete timeouts")
+                .build(),
+        )
+        .expect("expected successful migration"));
+
+        insta::assert_json_snapshot!(apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Delete {
+                    path: "arr[*].a".to_string()
+                })
+                .description("delete arr[*].a")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn delete_keeps_sentinel_like_values() {
+        let config = json!({
+            "obj": {
+                "field1": "__PLEASE_DELETE_ME",
+                "field2": 1
+            },
+            "arr": ["__PLEASE_DELETE_ME", "v2"]
+        });
+        let new_config = apply_migration(
+            &config,
+            &Migration::builder()
+                .action(Action::Delete {
+                    path: "obj.field2".to_string(),
+                })
+                .description("delete field2")
+                .build(),
+        )
+        .expect("expected successful migration");
+        assert_eq!(
+            new_config,
+            json!({
+                "obj": {
+                    "field1": "__PLEASE_DELETE_ME"
+                },
+                "arr": ["__PLEASE_DELETE_ME", "v2"]
+            })
+        );
+    }
+
+    #[test]
+    fn delete_every_array_element() {
+        let new_config = apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Delete {
+                    path: "arr[*]".to_string(),
+                })
+                .description("delete arr[*]")
+                .build(),
+        )
+        .expect("expected successful migration");
+        assert_eq!(new_config["arr"], json!([]));
+    }
+
+    #[test]
+    fn change_wildcard_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Change {
+                    path: "subgraphs.*.timeout".to_string(),
+                    from: Value::String("1s".into()),
+                    to: Value::String("10s".into()),
+                })
+                .description("change timeouts")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn move_wildcard_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Move {
+                    from: "subgraphs.*.timeout".to_string(),
+                    to: "subgraphs.*.http.timeout".to_string()
+                })
+                .description("move timeouts")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    fn merge_doc() -> Value {
+        json!({
+          "old": {
+                "a": 1,
+                "nested": {
+                    "x": 1
+                }
+            },
+          "new": {
+                "b": 2,
+                "nested": {
+                    "y": 2
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn merge_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &merge_doc(),
+            &Migration::builder()
+                .action(Action::Merge {
+                    from: "old".to_string(),
+                    to: "new".to_string()
+                })
+                .description("merge old into new")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn merge_into_non_existent_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &merge_doc(),
+            &Migration::builder()
+                .action(Action::Merge {
+                    from: "old.nested".to_string(),
+                    to: "missing".to_string()
+                })
+                .description("merge old.nested into missing")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn merge_non_existent_field() {
+        insta::assert_json_snapshot!(apply_migration(
+            &json!({"should": "stay"}),
+            &Migration::builder()
+                .action(Action::Merge {
+                    from: "old".to_string(),
+                    to: "new".to_string()
+                })
+                .description("merge old into new")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn when_field_equals() {
+        let migration = || {
+            Migration::builder()
+                .action(Action::When {
+                    path: "mode".to_string(),
+                    equals: Value::String("legacy".into()),
+                    then: vec![Action::Move {
+                        from: "x".to_string(),
+                        to: "y".to_string(),
+                    }],
+                })
+                .description("rename x to y in legacy mode")
+                .build()
+        };
+        insta::assert_json_snapshot!(apply_migration(
+            &json!({"mode": "legacy", "x": 1}),
+            &migration()
+        )
+        .expect("expected successful migration"));
+
+        // This one won't move the field because `mode` isn't `legacy`
+        insta::assert_json_snapshot!(apply_migration(
+            &json!({"mode": "modern", "x": 1}),
+            &migration()
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn when_from_yaml() {
+        let migration: Migration = serde_yaml::from_str(
+            r#"
+description: rename x to y in legacy mode
+actions:
+  - type: when
+    path: mode
+    equals: legacy
+    then:
+      - type: move
+        from: x
+        to: y
+"#,
+        )
+        .expect("migration must be valid");
+        assert_eq!(
+            apply_migration(&json!({"mode": "legacy", "x": 1}), &migration)
+                .expect("expected successful migration"),
+            json!({"mode": "legacy", "y": 1})
+        );
+    }
+
+    #[test]
+    fn upgrade_output_keeps_inline_comments() {
+        insta::assert_snapshot!(generate_upgrade_output(
+            "# header\nchanged: bar # why\nstable: 1.0 # keep me\nquoted: \"a # b\"\n",
+            "changed: bif\nstable: 1.0\nquoted: \"a # b\"\n",
+            true
+        )
+        .expect("expected successful migration"));
+    }
+
+    #[test]
+    fn embedded_migrations_are_valid() {
+        assert!(!load_migrations(&[])
+            .expect("embedded migrations must be valid")
+            .is_empty());
+    }
+
+    #[test]
+    fn extra_migrations_run_last() {
+        let extra = ConfigurationMigration::from_yaml(
+            "test-extra-migration",
+            r#"
+description: plugins.test.extra_old has been renamed to extra_new
+actions:
+  - type: move
+    from: plugins.test.extra_old
+    to: plugins.test.extra_new
+"#,
+        )
+        .expect("migration must be valid");
+
+        let migrations = load_migrations(&[extra.clone()]).expect("migrations must be valid");
+        assert_eq!(
+            migrations
+                .last()
+                .expect("there must be migrations")
+                .description,
+            "plugins.test.extra_old has been renamed to extra_new"
+        );
+        assert_eq!(
+            migrations
+                .iter()
+                .try_fold(
+                    json!({"plugins": {"test": {"extra_old": true}}}),
+                    |config, migration| apply_migration(&config, migration)
+                )
+                .expect("expected successful migration"),
+            json!({"plugins": {"test": {"extra_new": true}}})
+        );
+
+        let duplicate = ConfigurationMigration::from_yaml(
+            "test-extra-migration",
+            "description: duplicate\nactions: []",
+        )
+        .expect("migration must be valid");
+        let error = load_migrations(&[extra, duplicate])
+            .err()
+            .expect("migration names must be unique");
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration migration test-extra-migration: there are several migrations with this name"
+        );
+    }
+
+    #[test]
+    fn extra_migrations_are_ordered_by_name() {
+        let migration = |name: &str| {
+            ConfigurationMigration::from_yaml(name, &format!("description: {name}\nactions: []"))
+                .expect("migration must be valid")
+        };
+        let migrations = load_migrations(&[migration("0002-second"), migration("0001-first")])
+            .expect("migrations must be valid");
+        assert_eq!(
+            migrations
+                .iter()
+                .rev()
+                .take(2)
+                .map(|migration| migration.description.as_str())
+                .collect::<Vec<_>>(),
+            ["0002-second", "0001-first"]
+        );
+    }
+
+    #[test]
+    fn extra_migrations_are_validated() {
+        let error = ConfigurationMigration::from_yaml(
+            "test-invalid-migration",
+            "description: invalid\nactions:\n  - type: delete\n    path: plugins.test[name]\n",
+        )
+        .err()
+        .expect("expected invalid migration");
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration migration test-invalid-migration: invalid path 'plugins.test[name]'"
+        );
+    }
+
+    #[test]
+    fn combine_migration_errors() {
+        let error = combine_errors(vec![ConfigurationError::MigrationFailure {
+            error: "first".to_string(),
+        }]);
+        assert_eq!(error.to_string(), "could not migrate configuration: first.");
+
+        let error = combine_errors(vec![
+            ConfigurationError::MigrationFailure {
+                error: "first".to_string(),
+            },
+            ConfigurationError::InvalidMigration {
+                file: "second.yaml".to_string(),
+                error: "second".to_string(),
+            },
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "configuration could not be upgraded: \n1. could not migrate configuration: first.\n2. invalid configuration migration second.yaml: second"
+        );
+    }
+
+    #[test]
+    fn invalid_migration() {
+        let error = parse_migration(
+            "0000-unknown-action.yaml",
+            b"description: bad\nactions:\n  - type: unknown\n    path: a\n",
+        )
+        .err()
+        .expect("expected invalid migration");
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid configuration migration 0000-unknown-action.yaml: "),
+            "{error}"
+        );
+
+        let error = parse_migration(
+            "0000-unknown-level.yaml",
+            b"description: bad\nactions:\n  - type: log\n    path: a\n    level: loud\n    log: hello\n",
+        )
+        .err()
+        .expect("expected invalid migration");
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration migration 0000-unknown-level.yaml: unknown log level 'loud'"
+        );
+
+        let error = parse_migration(
+            "0000-invalid-path.yaml",
+            b"description: bad\nactions:\n  - type: when\n    path: a\n    equals: true\n    then:\n      - type: delete\n        path: a[b]\n",
+        )
+        .err()
+        .expect("expected invalid migration");
+        assert_eq!(
+            error.to_string(),
+            "invalid configuration migration 0000-invalid-path.yaml: invalid path 'a[b]'"
+        );
+
+        assert!(parse_migration(
+            "0000-invalid-move.yaml",
+            b"description: bad\nactions:\n  - type: move\n    from: a[b\n    to: c\n",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn applied_changes() {
+        let (_, changes) = apply_migration_with_changes(
+            &wildcard_doc(),
+            &Migration::builder()
+                .action(Action::Move {
+                    from: "subgraphs.*.timeout".to_string(),
+                    to: "subgraphs.*.http.timeout".to_string(),
+                })
+                .action(Action::Change {
+                    path: "subgraphs.products.retries".to_string(),
+                    from: 1.into(),
+                    to: 3.into(),
+                })
+                .action(Action::Delete {
+                    path: "missing".to_string(),
+                })
+                .description("update timeouts")
+                .build(),
+        )
+        .expect("expected successful migration");
+        insta::assert_json_snapshot!(changes);
+    }
+
+    #[test]
+    fn upgrade_changes_of_old_configuration() {
+        let (_, changes) = upgrade_configuration_with_changes(
+            &json!({
+                "health-check": {
+                    "enabled": true
+                }
+            }),
+            false,
+            &[],
+        )
+        .expect("expected successful migration");
+        assert_eq!(changes.len(), 2, "{changes:?}");
+        assert_eq!(changes[0].json_path, "$.health-check");
+        assert_eq!(changes[0].after, None);
+        assert_eq!(changes[1].json_path, "$.health_check");
+        assert_eq!(changes[1].after, Some(json!({"enabled": true})));
+    }
+
+    #[test]
+    fn coerce_field() {
+        let coerce = |path: &str, to_type: CoercionType| {
+            apply_migration(
+                &json!({
+                    "string": "5",
+                    "number": 5,
+                    "bool": true,
+                    "array": ["a"]
+                }),
+                &Migration::builder()
+                    .action(Action::Coerce {
+                        path: path.to_string(),
+                        to_type,
+                    })
+                    .description("coerce")
+                    .build(),
+            )
+        };
+        let coerced = |path: &str, to_type: CoercionType| {
+            coerce(path, to_type).expect("expected successful migration")[path].clone()
+        };
+        assert_eq!(coerced("string", CoercionType::Number), json!(5));
+        assert_eq!(coerced("number", CoercionType::String), json!("5"));
+        assert_eq!(coerced("bool", CoercionType::String), json!("true"));
+        assert_eq!(coerced("string", CoercionType::Array), json!(["5"]));
+        assert_eq!(coerced("array", CoercionType::Array), json!(["a"]));
+        assert_eq!(coerced("missing", CoercionType::Number), Value::Null);
+
+        let error = coerce("array", CoercionType::Number)
+            .err()
+            .expect("expected failed migration");
+        assert_eq!(
+            error.to_string(),
+            r#"could not migrate configuration: cannot convert ["a"] at array to number."#
+        );
+    }
+
+    #[test]
+    fn preserve_original_key_order() {
+        let original = json!({
+            "b": 1,
+            "a": {
+                "y": 1,
+                "x": 2
+            },
+            "arr": [{"n": 1, "m": 2}]
+        });
+        let mut value = json!({
+            "c": 1,
+            "arr": [{"m": 2, "n": 1}],
+            "a": {
+                "z": 3,
+                "x": 2,
+                "y": 1
+            },
+            "b": 1
+        });
+        preserve_key_order(&original, &mut value);
+        assert_eq!(
+            serde_json::to_string(&value).expect("value must serialize"),
+            r#"{"b":1,"a":{"y":1,"x":2,"z":3},"arr":[{"n":1,"m":2}],"c":1}"#
+        );
+    }
+
+    #[test]
+    fn upgrade_configuration_returns_changes() {
+        let (config, changes) = upgrade_configuration_with_changes(
+            &json!({
+                "health-check": {
+                    "enabled": true
+                }
+            }),
+            false,
+            &[],
+        )
+        .expect("expected successful migration");
+        assert_eq!(config, json!({"health_check": {"enabled": true}}));
+        assert!(
+            changes
+                .iter()
+                .all(|change| change.migration_description == changes[0].migration_description),
+            "{changes:?}"
+        );
+
+        let (_, changes) = upgrade_configuration_with_changes(&config, false, &[])
+            .expect("expected successful migration");
+        assert!(changes.is_empty(), "{changes:?}");
+    }
+
+    #[test]
+    fn upgrade_configuration_returns_applied_migrations() {
+        let (config, migrations) = upgrade_configuration_with_migrations(
+            &json!({
+                "health-check": {
+                    "enabled": true
+                },
+                "server": {
+                    "experimental_parser_recursion_limit": 100
+                }
+            }),
+            false,
+            &[],
+        )
+        .expect("expected successful migration");
+        assert_eq!(
+            config,
+            json!({
+                "health_check": {"enabled": true},
+                "limits": {"parser_max_recursion": 100}
+            })
+        );
+        // The parser recursion migration makes several changes, but is only listed once
+        assert_eq!(
+            migrations,
+            [
+                "health-check renamed to health_check",
+                "server.experimental_parser_recursion_limit moved to limits.parser_max_recursion, not experimental anymore. remove the server section, now empty",
+            ]
+        );
+
+        let (_, migrations) = upgrade_configuration_with_migrations(&config, false, &[])
+            .expect("expected successful migration");
+        assert!(migrations.is_empty(), "{migrations:?}");
+    }
+
+    #[test]
+    fn generate_upgrade_changes_as_json() {
+        let changes: Value = serde_json::from_str(
+            &generate_upgrade_changes("health-check:\n  enabled: true\n", &[])
+                .expect("expected successful migration"),
+        )
+        .expect("changes must be JSON");
+        assert_eq!(changes[1]["json_path"], "$.health_check");
+        assert_eq!(changes[1]["before"], Value::Null);
+        assert_eq!(changes[1]["after"], json!({"enabled": true}));
+    }
+
+    #[test]
+    fn upgrade_keeps_comments() {
+        let config = r#"# Router configuration
+health-check: # probes
+  listen: 0.0.0.0:8088
+server:
+  # Deeply nested queries are rejected
+  experimental_parser_recursion_limit: 100 # default is 4096
+limits:
+  # Bytes
+  experimental_http_max_request_bytes: 2000000 # 2MB
+"#;
+        assert_eq!(
+            generate_upgrade(config, false, &[]).expect("expected successful migration"),
+            r#"# Router configuration
+health_check: # probes
+  listen: 0.0.0.0:8088
+limits:
+  # Bytes
+  http_max_request_bytes: 2000000 # 2MB
+  # Deeply nested queries are rejected
+  parser_max_recursion: 100 # default is 4096
+"#
+        );
+
+        let diff = generate_upgrade(config, true, &[]).expect("expected successful migration");
+        assert!(diff.contains("\n   # Bytes\n"), "{diff}");
+        assert!(diff.contains("\n+health_check: # probes\n"), "{diff}");
+        assert!(
+            diff.contains("\n-  experimental_parser_recursion_limit: 100 # default is 4096\n"),
+            "{diff}"
+        );
+    }
+
+    #[test]
+    fn append_to_array() {
+        insta::assert_json_snapshot!(apply_migration(
+            &source_doc(),
+            &Migration::builder()
+                .action(Action::Append {
+                    path: "arr".to_string(),
+                    value: "v3".into(),
+                    skip_if_present: false,
+                })
+                .description("append v3")
+                .build(),
+        )
+        .expect("expected successful migration"));
+
+        // This one won't append because `v1` is already in the array
+        insta::assert_json_snapshot!(apply_migration(
+            &source_doc(),
+            &Migration::builder()
+                .action(Action::Append {
+                    path: "arr".to_string(),
+                    value: "v1".into(),
+                    skip_if_present: true,
+                })
+                .description("append v1")
+                .build(),
+        )
+        .expect("expected successful migration"));
+
+        // This one won't append because `missing` doesn't exist, we don't add parent structure
+        insta::assert_json_snapshot!(apply_migration(
+            &source_doc(),
+            &Migration::builder()
+                .action(Action::Append {
+                    path: "missing".to_string(),
+                    value: "v1".into(),
+                    skip_if_present: false,
+                })
+                .description("append v1")
+                .build(),
+        )
+        .expect("expected successful migration"));
+    }
 }
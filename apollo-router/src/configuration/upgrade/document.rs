@@ -0,0 +1,762 @@
+//! The configuration as it is written, edited in place by the upgrade so that the comments, blank
+//! lines and formatting of the parts that the upgrade does not change are kept.
+//!
+//! The changes made by the migrations are replayed on the document one at a time. Block mappings
+//! are edited entry by entry, and an entry that is moved takes its lines, comments included, along
+//! with it. Any other value that changes, such as a sequence or a flow mapping, is written out again
+//! as a whole.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::ops::Range;
+
+use serde_json::Map;
+use serde_json::Value;
+use yaml_rust::parser::MarkedEventReceiver;
+use yaml_rust::parser::Parser;
+use yaml_rust::scanner::Marker;
+use yaml_rust::Event;
+
+use super::delete_path;
+use super::parse_path;
+use super::AppliedChange;
+use super::ConfigLine;
+use super::PathSegment;
+
+/// How far the entries of a mapping created by the upgrade are indented from their parent.
+const INDENTATION: usize = 2;
+
+/// Applies `changes` to the YAML `config` in place. Returns `None` if the configuration cannot be
+/// edited in place, or if the edited configuration is not `upgraded_config`.
+pub(super) fn upgrade_document(
+    config: &str,
+    changes: &[AppliedChange],
+    upgraded_config: &Value,
+) -> Option<String> {
+    let mut document = Document::new(config);
+    let mut value = document.value()?;
+    let mut changes = changes.iter().peekable();
+    while let Some(change) = changes.next() {
+        let path = change_path(change)?;
+        set_path(&mut value, &path, change.after.as_ref())?;
+        if let Some(moved_to) = changes.next_if(|next| is_move(change, next)) {
+            let to = change_path(moved_to)?;
+            set_path(&mut value, &to, moved_to.after.as_ref())?;
+            document.move_entry(&path, &to);
+        }
+        document.reconcile(&value)?;
+    }
+    // The changes only cover the paths that the migrations wrote to or deleted
+    document.reconcile(upgraded_config)?;
+    (document.value()? == *upgraded_config).then(|| document.to_string())
+}
+
+fn change_path(change: &AppliedChange) -> Option<Vec<PathSegment>> {
+    parse_path(change.json_path.strip_prefix("$.")?)
+}
+
+/// Whether `from` and `to` are the two sides of a value moved from one path to another.
+fn is_move(from: &AppliedChange, to: &AppliedChange) -> bool {
+    from.action_summary.starts_with("move ")
+        && from.action_summary == to.action_summary
+        && from.migration_description == to.migration_description
+        && from.after.is_none()
+        && to.before.is_none()
+        && from.before.is_some()
+        && from.before == to.after
+}
+
+/// Sets the value at `path`, creating the objects that lead to it, or removes it if `new_value` is
+/// `None`.
+fn set_path(value: &mut Value, path: &[PathSegment], new_value: Option<&Value>) -> Option<()> {
+    let Some(new_value) = new_value else {
+        delete_path(value, path);
+        return Some(());
+    };
+    let mut current = value;
+    for segment in path {
+        current = match segment {
+            PathSegment::Key(key) => {
+                if !current.is_object() {
+                    *current = Value::Object(Map::new());
+                }
+                current
+                    .as_object_mut()?
+                    .entry(key.clone())
+                    .or_insert(Value::Null)
+            }
+            PathSegment::Index(index) => current.as_array_mut()?.get_mut(*index)?,
+            _ => return None,
+        };
+    }
+    *current = new_value.clone();
+    Some(())
+}
+
+/// The lines of a YAML document.
+struct Document {
+    lines: Vec<String>,
+}
+
+impl Document {
+    fn new(config: &str) -> Self {
+        // Yaml parser doesn't support CRLF. Remove CRs.
+        Self {
+            lines: config
+                .replace('\r', "")
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn value(&self) -> Option<Value> {
+        serde_yaml::from_str(&self.to_string()).ok()
+    }
+
+    /// Returns the root node of the document, or `None` if its structure cannot be edited in place.
+    /// An empty document is an empty block mapping.
+    fn root(&self) -> Option<Node> {
+        let source = self.to_string();
+        let mut index = Index {
+            lines: &self.lines,
+            stack: Vec::new(),
+            root: None,
+            complex_keys: false,
+        };
+        Parser::new(source.chars()).load(&mut index, false).ok()?;
+        if index.complex_keys || !index.stack.is_empty() {
+            return None;
+        }
+        Some(index.root.unwrap_or(Node::BlockMapping(Vec::new())))
+    }
+
+    /// Edits the document until its value is `value`, leaving the entries that already have the
+    /// right value as they are.
+    fn reconcile(&mut self, value: &Value) -> Option<()> {
+        let current = match self.value()? {
+            Value::Null => Map::new(),
+            Value::Object(current) => current,
+            _ => return None,
+        };
+        self.reconcile_mapping(&mut Vec::new(), &current, value.as_object()?)
+    }
+
+    fn reconcile_mapping(
+        &mut self,
+        path: &mut Vec<String>,
+        current: &Map<String, Value>,
+        value: &Map<String, Value>,
+    ) -> Option<()> {
+        for key in current.keys() {
+            if !value.contains_key(key) {
+                path.push(key.clone());
+                self.remove(path)?;
+                path.pop();
+            }
+        }
+        for (key, value) in value {
+            match current.get(key) {
+                None => self.insert(path, key, value)?,
+                Some(current) if current == value => {}
+                Some(current) => {
+                    path.push(key.clone());
+                    match (current, value) {
+                        // An empty mapping cannot be written as a block
+                        (Value::Object(current), Value::Object(value))
+                            if !value.is_empty() && self.is_block_mapping(path) =>
+                        {
+                            self.reconcile_mapping(path, current, value)?
+                        }
+                        _ => self.replace(path, value)?,
+                    }
+                    path.pop();
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn is_block_mapping(&self, path: &[String]) -> bool {
+        self.root()
+            .is_some_and(|root| matches!(node(&root, path), Some(Node::BlockMapping(_))))
+    }
+
+    /// Removes the entry at `path`, along with the comments above it.
+    fn remove(&mut self, path: &[String]) -> Option<()> {
+        let root = self.root()?;
+        let entry = entry(&root, path)?;
+        if !self.is_own_line(entry) {
+            return None;
+        }
+        let lines = self.comments_above(entry)..self.value_lines(entry).end;
+        self.lines.drain(lines);
+        Some(())
+    }
+
+    /// Replaces the value of the entry at `path`, keeping the comment at the end of its key's line.
+    fn replace(&mut self, path: &[String], value: &Value) -> Option<()> {
+        let root = self.root()?;
+        let entry = entry(&root, path)?;
+        if !self.is_own_line(entry) {
+            return None;
+        }
+        let key_line = &self.lines[entry.line];
+        let comment = &key_line[ConfigLine::new(key_line).without_comment.len()..];
+        let mut lines = render_entry(&entry.key, value, entry.column)?;
+        lines.first_mut()?.push_str(comment);
+        let replaced = self.value_lines(entry);
+        self.lines.splice(replaced, lines);
+        Some(())
+    }
+
+    /// Adds an entry to the end of the mapping at `path`.
+    fn insert(&mut self, path: &[String], key: &str, value: &Value) -> Option<()> {
+        let root = self.root()?;
+        let Node::BlockMapping(entries) = node(&root, path)? else {
+            return None;
+        };
+        let (line, column) = match entries.last() {
+            Some(last) => (self.value_lines(last).end, last.column),
+            None => (self.lines.len(), 0),
+        };
+        let lines = render_entry(key, value, column)?;
+        self.lines.splice(line..line, lines);
+        Some(())
+    }
+
+    /// Moves the lines of the entry at `from` to `to`, creating the mappings that lead to it. The
+    /// document is left as it was if the entry cannot be moved.
+    fn move_entry(&mut self, from: &[PathSegment], to: &[PathSegment]) {
+        let original = self.lines.clone();
+        if self.try_move_entry(from, to).is_none() || self.root().is_none() {
+            self.lines = original;
+        }
+    }
+
+    fn try_move_entry(&mut self, from: &[PathSegment], to: &[PathSegment]) -> Option<()> {
+        let (from, to) = (keys(from)?, keys(to)?);
+        let (key, parent) = to.split_last()?;
+        if to.starts_with(&from) {
+            return None;
+        }
+        let root = self.root()?;
+        let entry = entry(&root, &from)?;
+        if !self.is_own_line(entry) || node(&root, &to).is_some() {
+            return None;
+        }
+        // A renamed entry stays where it is
+        if from.split_last()?.1 == parent {
+            let line = &mut self.lines[entry.line];
+            let key_end = key_end(line, entry.column)?;
+            line.replace_range(entry.column..key_end, &render_key(key));
+            return Some(());
+        }
+
+        let start = self.comments_above(entry);
+        let key_line = entry.line - start;
+        let column = entry.column;
+        let end = self.value_lines(entry).end;
+        let mut lines: Vec<String> = self.lines.drain(start..end).collect();
+
+        // The entry goes at the end of the deepest mapping on its way that is already there
+        let root = self.root()?;
+        let mut entries = match &root {
+            Node::BlockMapping(entries) => entries,
+            _ => return None,
+        };
+        let mut depth = 0;
+        while let Some(entry) = parent
+            .get(depth)
+            .and_then(|key| entries.iter().find(|entry| &entry.key == key))
+        {
+            let Node::BlockMapping(children) = &entry.value else {
+                return None;
+            };
+            entries = children;
+            depth += 1;
+        }
+        let (line, parent_column) = match entries.last() {
+            Some(last) => (self.value_lines(last).end, last.column),
+            None => (self.lines.len(), 0),
+        };
+
+        let missing = &parent[depth..];
+        let new_column = parent_column + INDENTATION * missing.len();
+        for line in &mut lines {
+            reindent(line, column, new_column);
+        }
+        let key_line = &mut lines[key_line];
+        let key_end = key_end(key_line, new_column)?;
+        key_line.replace_range(new_column..key_end, &render_key(key));
+
+        let parents = missing.iter().enumerate().map(|(depth, key)| {
+            format!(
+                "{:indentation$}{}:",
+                "",
+                render_key(key),
+                indentation = parent_column + INDENTATION * depth
+            )
+        });
+        self.lines
+            .splice(line..line, parents.chain(lines).collect::<Vec<_>>());
+        Some(())
+    }
+
+    /// Whether the key of `entry` starts its line, rather than following a `- ` or `? `.
+    fn is_own_line(&self, entry: &Entry) -> bool {
+        indentation(&self.lines[entry.line]) == Some(entry.column)
+    }
+
+    /// Returns the first line of the comments directly above `entry`, at its indentation.
+    fn comments_above(&self, entry: &Entry) -> usize {
+        let mut start = entry.line;
+        while start > 0 && is_comment(&self.lines[start - 1], entry.column) {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Returns the lines of `entry`, from its key to the last line of its value. The comments and
+    /// blank lines after the value are left out, as they are about what follows.
+    fn value_lines(&self, entry: &Entry) -> Range<usize> {
+        let mut end = entry.line + 1;
+        for (index, line) in self.lines.iter().enumerate().skip(entry.line + 1) {
+            let Some(indentation) = indentation(line) else {
+                continue;
+            };
+            // The items of a sequence can be at the same indentation as its key
+            let sequence_item =
+                matches!(entry.value, Node::Sequence) && line[indentation..].starts_with('-');
+            if indentation < entry.column || (indentation == entry.column && !sequence_item) {
+                break;
+            }
+            end = index + 1;
+        }
+        entry.line..end
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A node of the document. Only block mappings are kept track of, as they are the only nodes that
+/// are edited rather than written out again.
+enum Node {
+    BlockMapping(Vec<Entry>),
+    Sequence,
+    Other,
+}
+
+/// An entry of a block mapping.
+struct Entry {
+    key: String,
+    /// The line of the key
+    line: usize,
+    /// The column of the key
+    column: usize,
+    value: Node,
+}
+
+fn node<'a>(root: &'a Node, path: &[String]) -> Option<&'a Node> {
+    if path.is_empty() {
+        Some(root)
+    } else {
+        entry(root, path).map(|entry| &entry.value)
+    }
+}
+
+fn entry<'a>(root: &'a Node, path: &[String]) -> Option<&'a Entry> {
+    let (key, parent) = path.split_last()?;
+    let Node::BlockMapping(entries) = node(root, parent)? else {
+        return None;
+    };
+    entries.iter().find(|entry| &entry.key == key)
+}
+
+fn keys(path: &[PathSegment]) -> Option<Vec<String>> {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Builds the nodes of a document from its parse events.
+struct Index<'a> {
+    lines: &'a [String],
+    stack: Vec<Partial>,
+    root: Option<Node>,
+    complex_keys: bool,
+}
+
+/// A collection that is being parsed.
+enum Partial {
+    Mapping {
+        block: bool,
+        entries: Vec<Entry>,
+        key: Option<(String, Marker)>,
+    },
+    Sequence,
+}
+
+impl Index<'_> {
+    fn add(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            None => self.root = Some(node),
+            Some(Partial::Mapping { entries, key, .. }) => match key.take() {
+                Some((key, marker)) => entries.push(Entry {
+                    key,
+                    line: marker.line().saturating_sub(1),
+                    column: marker.col(),
+                    value: node,
+                }),
+                // A key that is a collection or an alias, which cannot be looked up
+                None => self.complex_keys = true,
+            },
+            Some(Partial::Sequence) => {}
+        }
+    }
+
+    fn starts_with(&self, marker: &Marker, c: char) -> bool {
+        self.lines
+            .get(marker.line().saturating_sub(1))
+            .and_then(|line| line.chars().nth(marker.col()))
+            == Some(c)
+    }
+}
+
+impl MarkedEventReceiver for Index<'_> {
+    fn on_event(&mut self, ev: Event, marker: Marker) {
+        match ev {
+            Event::Scalar(value, ..) => match self.stack.last_mut() {
+                Some(Partial::Mapping {
+                    key: key @ None, ..
+                }) => *key = Some((value, marker)),
+                _ => self.add(Node::Other),
+            },
+            Event::Alias(_) => self.add(Node::Other),
+            Event::MappingStart(_) => {
+                let block = !self.starts_with(&marker, '{');
+                self.stack.push(Partial::Mapping {
+                    block,
+                    entries: Vec::new(),
+                    key: None,
+                })
+            }
+            Event::SequenceStart(_) => self.stack.push(Partial::Sequence),
+            Event::MappingEnd => match self.stack.pop() {
+                Some(Partial::Mapping {
+                    block: true,
+                    entries,
+                    ..
+                }) if !entries.is_empty() => self.add(Node::BlockMapping(entries)),
+                _ => self.add(Node::Other),
+            },
+            Event::SequenceEnd => {
+                self.stack.pop();
+                self.add(Node::Sequence);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the indentation of a line, or `None` if it is blank or a comment.
+fn indentation(line: &str) -> Option<usize> {
+    let content = line.trim_start_matches(' ');
+    (!content.trim().is_empty() && !content.starts_with('#')).then_some(line.len() - content.len())
+}
+
+fn is_comment(line: &str, column: usize) -> bool {
+    let content = line.trim_start_matches(' ');
+    content.starts_with('#') && line.len() - content.len() == column
+}
+
+/// Moves a line from one indentation to another, keeping the indentation of the lines under it
+/// relative to it.
+fn reindent(line: &mut String, from: usize, to: usize) {
+    if to > from {
+        if !line.trim().is_empty() {
+            line.insert_str(0, &" ".repeat(to - from));
+        }
+    } else {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        line.drain(..spaces.min(from - to));
+    }
+}
+
+/// Returns where the key that starts at `column` ends.
+fn key_end(line: &str, column: usize) -> Option<usize> {
+    let key = line.get(column..)?;
+    let length = match key.chars().next()? {
+        quote @ ('"' | '\'') => key[1..].find(quote)? + 2,
+        _ => {
+            key.match_indices(':')
+                .find(|(index, _)| {
+                    key[index + 1..].is_empty() || key[index + 1..].starts_with([' ', '\t'])
+                })?
+                .0
+        }
+    };
+    Some(column + length)
+}
+
+fn render_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+/// Writes out an entry as YAML, starting at `column`.
+fn render_entry(key: &str, value: &Value, column: usize) -> Option<Vec<String>> {
+    let mut entry = Map::new();
+    entry.insert(key.to_string(), value.clone());
+    let yaml = serde_yaml::to_string(&entry).ok()?;
+    Some(
+        yaml.strip_prefix("---\n")
+            .unwrap_or(&yaml)
+            .lines()
+            .map(|line| match line {
+                "" => String::new(),
+                line => format!("{:column$}{line}", ""),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use serde_json::Value;
+
+    use crate::configuration::upgrade::document::upgrade_document;
+    use crate::configuration::upgrade::AppliedChange;
+
+    fn change(path: &str, before: Option<Value>, after: Option<Value>) -> AppliedChange {
+        AppliedChange {
+            migration_description: "test migration".to_string(),
+            action_summary: "test action".to_string(),
+            json_path: format!("$.{path}"),
+            before,
+            after,
+        }
+    }
+
+    fn moved(from: &str, to: &str, value: Value) -> [AppliedChange; 2] {
+        let action_summary = format!("move {from} to {to}");
+        let [mut from, mut to] = [
+            change(from, Some(value.clone()), None),
+            change(to, None, Some(value)),
+        ];
+        from.action_summary = action_summary.clone();
+        to.action_summary = action_summary;
+        [from, to]
+    }
+
+    fn upgrade(config: &str, changes: &[AppliedChange], upgraded_config: Value) -> Option<String> {
+        upgrade_document(config, changes, &upgraded_config)
+    }
+
+    #[test]
+    fn change_keeps_comments() {
+        let config = r#"# The router configuration
+
+supergraph:
+  # Where the router listens
+  listen: 127.0.0.1:4000 # the default
+  path: /graphql # keep me
+"#;
+        assert_eq!(
+            upgrade(
+                config,
+                &[change(
+                    "supergraph.listen",
+                    Some(json!("127.0.0.1:4000")),
+                    Some(json!("0.0.0.0:4000"))
+                )],
+                json!({"supergraph": {"listen": "0.0.0.0:4000", "path": "/graphql"}})
+            )
+            .as_deref(),
+            Some(
+                r#"# The router configuration
+
+supergraph:
+  # Where the router listens
+  listen: "0.0.0.0:4000" # the default
+  path: /graphql # keep me
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn rename_keeps_the_entry_in_place() {
+        let config = r#"health-check: # probes
+  # Where the health check listens
+  listen: 0.0.0.0:8088
+supergraph:
+  path: /graphql
+"#;
+        let changes = moved(
+            "health-check",
+            "health_check",
+            json!({"listen": "0.0.0.0:8088"}),
+        );
+        assert_eq!(
+            upgrade(
+                config,
+                &changes,
+                json!({"supergraph": {"path": "/graphql"}, "health_check": {"listen": "0.0.0.0:8088"}})
+            )
+            .as_deref(),
+            Some(
+                r#"health_check: # probes
+  # Where the health check listens
+  listen: 0.0.0.0:8088
+supergraph:
+  path: /graphql
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn move_takes_comments_along() {
+        let config = r#"telemetry:
+  tracing:
+    # Sent to the collector
+    otlp: # over grpc
+      endpoint: default # the collector
+  metrics:
+    common:
+      service_name: router
+"#;
+        let changes = moved(
+            "telemetry.tracing.otlp",
+            "telemetry.exporters.tracing.otlp",
+            json!({"endpoint": "default"}),
+        );
+        assert_eq!(
+            upgrade(
+                config,
+                &changes,
+                json!({"telemetry": {
+                    "tracing": {},
+                    "metrics": {"common": {"service_name": "router"}},
+                    "exporters": {"tracing": {"otlp": {"endpoint": "default"}}}
+                }})
+            )
+            .as_deref(),
+            Some(
+                r#"telemetry:
+  tracing: {}
+  metrics:
+    common:
+      service_name: router
+  exporters:
+    tracing:
+      # Sent to the collector
+      otlp: # over grpc
+        endpoint: default # the collector
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn delete_and_add_keep_other_comments() {
+        let config = r#"limits:
+  # No longer used
+  old_limit: 10
+  # Still used
+  max_depth: 100 # deep enough
+
+# Everything else
+cors:
+  origins:
+    - https://studio.apollographql.com
+"#;
+        assert_eq!(
+            upgrade(
+                config,
+                &[
+                    change("limits.old_limit", Some(json!(10)), None),
+                    change("limits.max_height", None, Some(json!(200))),
+                    change(
+                        "cors.origins",
+                        Some(json!(["https://studio.apollographql.com"])),
+                        Some(json!([
+                            "https://studio.apollographql.com",
+                            "https://example.com"
+                        ]))
+                    ),
+                ],
+                json!({
+                    "limits": {"max_depth": 100, "max_height": 200},
+                    "cors": {"origins": ["https://studio.apollographql.com", "https://example.com"]}
+                })
+            )
+            .as_deref(),
+            Some(
+                r#"limits:
+  # Still used
+  max_depth: 100 # deep enough
+  max_height: 200
+
+# Everything else
+cors:
+  origins:
+    - "https://studio.apollographql.com"
+    - "https://example.com"
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn flow_mappings_are_written_out_again() {
+        assert_eq!(
+            upgrade(
+                "limits: {max_depth: 100} # inline\n",
+                &[change(
+                    "limits.max_depth",
+                    Some(json!(100)),
+                    Some(json!(50))
+                )],
+                json!({"limits": {"max_depth": 50}})
+            )
+            .as_deref(),
+            Some("limits: # inline\n  max_depth: 50\n")
+        );
+    }
+
+    #[test]
+    fn flow_documents_are_not_edited() {
+        assert_eq!(
+            upgrade(
+                "{limits: {max_depth: 100}}\n",
+                &[change(
+                    "limits.max_depth",
+                    Some(json!(100)),
+                    Some(json!(50))
+                )],
+                json!({"limits": {"max_depth": 50}})
+            ),
+            None
+        );
+    }
+}
@@ -19,6 +19,7 @@ use clap::Args;
 use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
 #[cfg(any(feature = "dhat-heap", feature = "dhat-ad-hoc"))]
 use once_cell::sync::OnceCell;
 use regex::Captures;
@@ -28,6 +29,8 @@ use url::Url;
 
 use crate::configuration::generate_config_schema;
 use crate::configuration::generate_upgrade;
+use crate::configuration::generate_upgrade_changes;
+use crate::configuration::ConfigurationMigration;
 use crate::configuration::Discussed;
 use crate::metrics::meter_provider;
 use crate::plugin::plugins;
@@ -139,6 +142,10 @@ enum ConfigSubcommand {
         /// Print a diff.
         #[clap(action = ArgAction::SetTrue, long)]
         diff: bool,
+
+        /// The format to print the upgrade in.
+        #[clap(value_enum, long, default_value_t = UpgradeFormat::Yaml, conflicts_with = "diff")]
+        format: UpgradeFormat,
     },
     /// List all the available experimental configurations with related GitHub discussion
     Experimental,
@@ -146,6 +153,15 @@ enum ConfigSubcommand {
     Preview,
 }
 
+/// The formats that `router config upgrade` can print the upgrade in.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum UpgradeFormat {
+    /// The upgraded configuration, as YAML.
+    Yaml,
+    /// The changes that the upgrade makes to the configuration, as JSON.
+    Json,
+}
+
 /// Options for the router
 #[derive(Parser, Debug)]
 #[clap(name = "router", about = "Apollo federation router")]
@@ -364,6 +380,11 @@ impl Executable {
     ///   Specifies when to find the supergraph schema.
     ///   The default is the file specified by the `--supergraph` or `-s` CLI option.
     ///
+    /// * `.configuration_migration(impl Into<`[`ConfigurationMigration`]`>)`
+    ///   Optional, can be called several times.
+    ///   Adds a migration that is applied after the Router's own migrations,
+    ///   both to the configuration file and by `router config upgrade`.
+    ///
     /// * `.shutdown(impl Into<`[`ShutdownSource`]`>)`
     ///   Optional.
     ///   Specifies when the Router should shut down gracefully.
@@ -397,6 +418,7 @@ impl Executable {
         license: Option<LicenseSource>,
         config: Option<ConfigurationSource>,
         cli_args: Option<Opt>,
+        configuration_migrations: Vec<ConfigurationMigration>,
     ) -> Result<()> {
         let opt = cli_args.unwrap_or_else(Opt::parse);
 
@@ -433,10 +455,22 @@ impl Executable {
                 Ok(())
             }
             Some(Commands::Config(ConfigSubcommandArgs {
-                command: ConfigSubcommand::Upgrade { config_path, diff },
+                command:
+                    ConfigSubcommand::Upgrade {
+                        config_path,
+                        diff,
+                        format,
+                    },
             })) => {
                 let config_string = std::fs::read_to_string(config_path)?;
-                let output = generate_upgrade(&config_string, *diff)?;
+                let output = match format {
+                    UpgradeFormat::Yaml => {
+                        generate_upgrade(&config_string, *diff, &configuration_migrations)?
+                    }
+                    UpgradeFormat::Json => {
+                        generate_upgrade_changes(&config_string, &configuration_migrations)?
+                    }
+                };
                 println!("{output}");
                 Ok(())
             }
@@ -452,7 +486,17 @@ impl Executable {
                 Discussed::new().print_preview();
                 Ok(())
             }
-            None => Self::inner_start(shutdown, schema, config, license, opt).await,
+            None => {
+                Self::inner_start(
+                    shutdown,
+                    schema,
+                    config,
+                    configuration_migrations,
+                    license,
+                    opt,
+                )
+                .await
+            }
         };
 
         if apollo_telemetry_initialized {
@@ -470,6 +514,7 @@ impl Executable {
         shutdown: Option<ShutdownSource>,
         schema: Option<SchemaSource>,
         config: Option<ConfigurationSource>,
+        configuration_migrations: Vec<ConfigurationMigration>,
         license: Option<LicenseSource>,
         mut opt: Opt,
     ) -> Result<()> {
@@ -717,6 +762,7 @@ impl Executable {
         let router = RouterHttpServer::builder()
             .is_telemetry_disabled(opt.is_telemetry_disabled())
             .configuration(configuration)
+            .configuration_migrations(configuration_migrations)
             .and_uplink(uplink_config)
             .schema(schema_source)
             .license(license)
@@ -81,8 +81,10 @@ pub mod test_harness;
 pub mod tracer;
 mod uplink;
 
+pub use crate::axum_factory::compression::ResponseCompressor;
 pub use crate::axum_factory::unsupported_set_axum_router_callback;
 pub use crate::configuration::Configuration;
+pub use crate::configuration::ConfigurationMigration;
 pub use crate::configuration::ListenAddr;
 pub use crate::context::extensions::sync::ExtensionsMutex;
 pub use crate::context::extensions::Extensions;
@@ -61,6 +61,9 @@ struct Shaping {
     deduplicate_query: Option<bool>,
     /// Enable compression for subgraphs (available compressions are deflate, br, gzip)
     compression: Option<Compression>,
+    /// Requests with a body smaller than this number of bytes are sent uncompressed. Defaults to
+    /// `supergraph.compression_min_size`
+    compression_min_size: Option<usize>,
     /// Enable global rate limiting
     global_rate_limit: Option<RateLimitConf>,
     #[serde(deserialize_with = "humantime_serde::deserialize", default)]
@@ -93,6 +96,7 @@ impl Merge for Shaping {
             Some(fallback) => Shaping {
                 deduplicate_query: self.deduplicate_query.or(fallback.deduplicate_query),
                 compression: self.compression.or(fallback.compression),
+                compression_min_size: self.compression_min_size.or(fallback.compression_min_size),
                 timeout: self.timeout.or(fallback.timeout),
                 global_rate_limit: self
                     .global_rate_limit
@@ -452,6 +456,14 @@ impl TrafficShaping {
         .and_then(|config| config.shaping.experimental_http2)
         .unwrap_or(Http2Config::Enable)
     }
+
+    pub(crate) fn subgraph_compression_min_size(&self, service_name: &str) -> Option<usize> {
+        Self::merge_config(
+            self.config.all.as_ref(),
+            self.config.subgraphs.get(service_name),
+        )
+        .and_then(|config| config.shaping.compression_min_size)
+    }
 }
 
 register_plugin!("apollo", "traffic_shaping", TrafficShaping);
@@ -774,6 +786,48 @@ mod test {
         assert!(shaping_config.enable_subgraph_http2("this_doesnt_exist") == Http2Config::Disable);
     }
 
+    #[tokio::test]
+    async fn test_subgraph_compression_min_size() {
+        let config = serde_yaml::from_str::<Config>(
+            r#"
+        all:
+          compression_min_size: 1024
+        subgraphs:
+          products:
+            compression_min_size: 0
+          reviews:
+            compression: gzip
+        "#,
+        )
+        .unwrap();
+
+        let shaping_config = TrafficShaping::new(PluginInit::fake_builder().config(config).build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            shaping_config.subgraph_compression_min_size("products"),
+            Some(0)
+        );
+        assert_eq!(
+            shaping_config.subgraph_compression_min_size("reviews"),
+            Some(1024)
+        );
+        assert_eq!(
+            shaping_config.subgraph_compression_min_size("this_doesnt_exist"),
+            Some(1024)
+        );
+
+        let config = serde_yaml::from_str::<Config>("{}").unwrap();
+        let shaping_config = TrafficShaping::new(PluginInit::fake_builder().config(config).build())
+            .await
+            .unwrap();
+        assert_eq!(
+            shaping_config.subgraph_compression_min_size("products"),
+            None
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn it_rate_limit_subgraph_requests() {
         let config = serde_yaml::from_str::<serde_json::Value>(
@@ -1,6 +1,7 @@
 use std::path::Path;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 
 use derivative::Derivative;
@@ -13,6 +14,7 @@ use crate::router::Event::NoMoreConfiguration;
 use crate::router::Event::UpdateConfiguration;
 use crate::uplink::UplinkConfig;
 use crate::Configuration;
+use crate::ConfigurationMigration;
 
 type ConfigurationStream = Pin<Box<dyn Stream<Item = Configuration> + Send>>;
 
@@ -58,9 +60,12 @@ impl Default for ConfigurationSource {
 
 impl ConfigurationSource {
     /// Convert this config into a stream regardless of if is static or not. Allows for unified handling later.
+    ///
+    /// `extra_migrations` are applied after the router's own migrations to configuration read from a file.
     pub(crate) fn into_stream(
         self,
         uplink_config: Option<UplinkConfig>,
+        extra_migrations: Vec<ConfigurationMigration>,
     ) -> impl Stream<Item = Event> {
         match self {
             ConfigurationSource::Static(mut instance) => {
@@ -87,16 +92,21 @@ impl ConfigurationSource {
                     );
                     stream::empty().boxed()
                 } else {
-                    match ConfigurationSource::read_config(&path) {
+                    match ConfigurationSource::read_config(&path, &extra_migrations) {
                         Ok(mut configuration) => {
                             if watch {
+                                let extra_migrations = Arc::new(extra_migrations);
                                 crate::files::watch(&path)
                                     .filter_map(move |_| {
                                         let path = path.clone();
                                         let uplink_config = uplink_config.clone();
+                                        let extra_migrations = extra_migrations.clone();
                                         async move {
-                                            match ConfigurationSource::read_config_async(&path)
-                                                .await
+                                            match ConfigurationSource::read_config_async(
+                                                &path,
+                                                &extra_migrations,
+                                            )
+                                            .await
                                             {
                                                 Ok(mut configuration) => {
                                                     configuration.uplink = uplink_config.clone();
@@ -128,13 +138,21 @@ impl ConfigurationSource {
         .boxed()
     }
 
-    fn read_config(path: &Path) -> Result<Configuration, ReadConfigError> {
+    fn read_config(
+        path: &Path,
+        extra_migrations: &[ConfigurationMigration],
+    ) -> Result<Configuration, ReadConfigError> {
         let config = std::fs::read_to_string(path)?;
-        config.parse().map_err(ReadConfigError::Validation)
+        Configuration::from_str_with_migrations(&config, extra_migrations)
+            .map_err(ReadConfigError::Validation)
     }
-    async fn read_config_async(path: &Path) -> Result<Configuration, ReadConfigError> {
+    async fn read_config_async(
+        path: &Path,
+        extra_migrations: &[ConfigurationMigration],
+    ) -> Result<Configuration, ReadConfigError> {
         let config = tokio::fs::read_to_string(path).await?;
-        config.parse().map_err(ReadConfigError::Validation)
+        Configuration::from_str_with_migrations(&config, extra_migrations)
+            .map_err(ReadConfigError::Validation)
     }
 }
 
@@ -165,7 +183,7 @@ mod tests {
             watch: true,
             delay: None,
         }
-        .into_stream(Some(UplinkConfig::default()))
+        .into_stream(Some(UplinkConfig::default()), vec![])
         .boxed();
 
         // First update is guaranteed
@@ -196,7 +214,7 @@ mod tests {
             watch: true,
             delay: None,
         }
-        .into_stream(Some(UplinkConfig::default()));
+        .into_stream(Some(UplinkConfig::default()), vec![]);
 
         // First update fails because the file is invalid.
         assert!(matches!(stream.next().await.unwrap(), NoMoreConfiguration));
@@ -211,7 +229,7 @@ mod tests {
             watch: true,
             delay: None,
         }
-        .into_stream(Some(UplinkConfig::default()));
+        .into_stream(Some(UplinkConfig::default()), vec![]);
 
         // First update fails because the file is invalid.
         assert!(matches!(stream.next().await.unwrap(), NoMoreConfiguration));
@@ -228,7 +246,7 @@ mod tests {
             watch: false,
             delay: None,
         }
-        .into_stream(Some(UplinkConfig::default()));
+        .into_stream(Some(UplinkConfig::default()), vec![]);
         assert!(matches!(
             stream.next().await.unwrap(),
             UpdateConfiguration(_)
@@ -30,6 +30,7 @@ use tokio::task::spawn;
 use tracing_futures::WithSubscriber;
 
 use crate::axum_factory::AxumHttpServerFactory;
+use crate::configuration::ConfigurationMigration;
 use crate::configuration::ListenAddr;
 use crate::orbiter::OrbiterRouterSuperServiceFactory;
 use crate::router_factory::YamlRouterFactory;
@@ -103,6 +104,11 @@ impl RouterHttpServer {
     ///   Optional.
     ///   Specifies the Uplink configuration options.
     ///
+    /// * `.configuration_migration(impl Into<`[`ConfigurationMigration`]`>)`
+    ///   Optional, can be called several times.
+    ///   Adds a migration that is applied after the router's own migrations
+    ///   to configuration read from a file.
+    ///
     /// * `.shutdown(impl Into<`[`ShutdownSource`]`>)`
     ///   Optional.
     ///   Specifies when the server should gracefully shut down.
@@ -130,11 +136,13 @@ impl RouterHttpServer {
         shutdown: Option<ShutdownSource>,
         uplink: Option<UplinkConfig>,
         is_telemetry_disabled: Option<bool>,
+        configuration_migrations: Vec<ConfigurationMigration>,
     ) -> RouterHttpServer {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
         let event_stream = generate_event_stream(
             shutdown.unwrap_or(ShutdownSource::CtrlC),
             configuration.unwrap_or_default(),
+            configuration_migrations,
             schema,
             uplink,
             license.unwrap_or_default(),
@@ -228,6 +236,7 @@ impl Future for RouterHttpServer {
 fn generate_event_stream(
     shutdown: ShutdownSource,
     configuration: ConfigurationSource,
+    configuration_migrations: Vec<ConfigurationMigration>,
     schema: SchemaSource,
     uplink_config: Option<UplinkConfig>,
     license: LicenseSource,
@@ -241,7 +250,7 @@ fn generate_event_stream(
         license.into_stream().boxed(),
         reload_source.clone().into_stream().boxed(),
         configuration
-            .into_stream(uplink_config)
+            .into_stream(uplink_config, configuration_migrations)
             .map(move |config_event| {
                 if let Event::UpdateConfiguration(config) = &config_event {
                     reload_source.set_period(&config.experimental_chaos.force_reload)
@@ -417,6 +417,7 @@ pub(crate) async fn create_subgraph_services(
             configuration,
             &tls_root_store,
             shaping.enable_subgraph_http2(name),
+            shaping.subgraph_compression_min_size(name),
         )?;
 
         let http_service_factory = HttpClientServiceFactory::new(http_service, plugins.clone());
@@ -56,6 +56,7 @@ impl HttpClientServiceFactory {
             configuration,
             &rustls::RootCertStore::empty(),
             http2,
+            None,
         )
         .unwrap();
 
@@ -33,7 +33,9 @@ use tracing::Instrument;
 
 use super::HttpRequest;
 use super::HttpResponse;
+use crate::axum_factory::compression::peek_compressible;
 use crate::axum_factory::compression::Compressor;
+use crate::axum_factory::compression::DEFAULT_MIN_SIZE;
 use crate::configuration::TlsClientAuth;
 use crate::error::FetchError;
 use crate::plugins::authentication::subgraph::SigningParamsConfig;
@@ -96,6 +98,7 @@ pub(crate) struct HttpClientService {
     #[cfg(unix)]
     unix_client: UnixHTTPClient,
     service: Arc<String>,
+    compression_min_size: usize,
 }
 
 impl HttpClientService {
@@ -104,6 +107,7 @@ impl HttpClientService {
         configuration: &Configuration,
         tls_root_store: &RootCertStore,
         http2: Http2Config,
+        compression_min_size: Option<usize>,
     ) -> Result<Self, BoxError> {
         let name: String = service.into();
         let tls_cert_store = configuration
@@ -131,7 +135,10 @@ impl HttpClientService {
 
         let tls_client_config = generate_tls_client_config(tls_cert_store, client_cert_config)?;
 
-        HttpClientService::new(name, http2, tls_client_config)
+        let mut service = HttpClientService::new(name, http2, tls_client_config)?;
+        service.compression_min_size =
+            compression_min_size.unwrap_or(configuration.supergraph.compression_min_size);
+        Ok(service)
     }
 
     pub(crate) fn new(
@@ -168,6 +175,7 @@ impl HttpClientService {
                 .layer(DecompressionLayer::new())
                 .service(hyper::Client::builder().build(UnixConnector)),
             service: Arc::new(service.into()),
+            compression_min_size: DEFAULT_MIN_SIZE,
         })
     }
 
@@ -255,6 +263,7 @@ impl tower::Service<HttpRequest> for HttpClientService {
         let client = self.http_client.clone();
 
         let service_name = self.service.clone();
+        let compression_min_size = self.compression_min_size;
 
         let path = schema_uri.path();
 
@@ -275,7 +284,7 @@ impl tower::Service<HttpRequest> for HttpClientService {
             );
         });
 
-        let (parts, body) = http_request.into_parts();
+        let (mut parts, body) = http_request.into_parts();
 
         let content_encoding = parts.headers.get(&CONTENT_ENCODING);
         let opt_compressor = content_encoding
@@ -283,21 +292,30 @@ impl tower::Service<HttpRequest> for HttpClientService {
             .and_then(|value| value.to_str().ok())
             .and_then(|v| Compressor::new(v.split(',').map(|s| s.trim())));
 
-        let body = match opt_compressor {
-            None => body,
-            Some(compressor) => RouterBody::wrap_stream(compressor.process(body)),
-        };
-        let mut http_request = http::Request::from_parts(parts, body);
-
-        http_request
-            .headers_mut()
-            .insert(ACCEPT_ENCODING, ACCEPTED_ENCODINGS.clone());
-
         let signing_params = context
             .extensions()
             .with_lock(|lock| lock.get::<Arc<SigningParamsConfig>>().cloned());
 
         Box::pin(async move {
+            let body = match opt_compressor {
+                None => body,
+                Some(compressor) => {
+                    // small operations are sent as is, compressing them would only add overhead
+                    let (body, compress) = peek_compressible(body, compression_min_size).await;
+                    if compress {
+                        RouterBody::wrap_stream(compressor.process(body))
+                    } else {
+                        parts.headers.remove(CONTENT_ENCODING);
+                        body
+                    }
+                }
+            };
+            let mut http_request = http::Request::from_parts(parts, body);
+
+            http_request
+                .headers_mut()
+                .insert(ACCEPT_ENCODING, ACCEPTED_ENCODINGS.clone());
+
             let http_request = if let Some(signing_params) = signing_params {
                 signing_params.sign(http_request, &service_name).await?
             } else {
@@ -117,6 +117,7 @@ async fn tls_self_signed() {
         &config,
         &rustls::RootCertStore::empty(),
         Http2Config::Enable,
+        None,
     )
     .unwrap();
 
@@ -174,6 +175,7 @@ async fn tls_custom_root() {
         &config,
         &rustls::RootCertStore::empty(),
         Http2Config::Enable,
+        None,
     )
     .unwrap();
 
@@ -284,6 +286,7 @@ async fn tls_client_auth() {
         &config,
         &rustls::RootCertStore::empty(),
         Http2Config::Enable,
+        None,
     )
     .unwrap();
 
@@ -452,6 +455,107 @@ async fn test_compressed_request_response_body() {
     );
 }
 
+// starts a local server emulating a subgraph that expects an uncompressed request
+async fn emulate_subgraph_uncompressed_request(listener: TcpListener) {
+    async fn handle(request: http::Request<Body>) -> Result<http::Response<Body>, Infallible> {
+        assert!(request.headers().get(CONTENT_ENCODING).is_none());
+        let body = get_body_bytes(request.into_body()).await.unwrap();
+        assert_eq!(r#"{"query":"{ me }"}"#, std::str::from_utf8(&body).unwrap());
+
+        Ok(http::Response::builder()
+            .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+            .status(StatusCode::OK)
+            .body(r#"{"data":"test"}"#.into())
+            .unwrap())
+    }
+
+    let make_svc = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+    let server = Server::from_tcp(listener).unwrap().serve(make_svc);
+    server.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_small_request_body_is_not_compressed() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(emulate_subgraph_uncompressed_request(listener));
+    let subgraph_service = HttpClientService::new(
+        "test",
+        Http2Config::Http2Only,
+        rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_native_roots()
+            .with_no_client_auth(),
+    )
+    .expect("can create a HttpService");
+
+    let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+    let response = subgraph_service
+        .oneshot(HttpRequest {
+            http_request: http::Request::builder()
+                .uri(url)
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .header(CONTENT_ENCODING, "gzip")
+                .body(r#"{"query":"{ me }"}"#.into())
+                .unwrap(),
+            context: Context::new(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::str::from_utf8(
+            &get_body_bytes(response.http_response.into_parts().1)
+                .await
+                .unwrap()
+        )
+        .unwrap(),
+        r#"{"data":"test"}"#
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subgraph_compression_min_size_overrides_supergraph() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(emulate_subgraph_compressed_response(listener));
+
+    let mut config = Configuration::default();
+    config.supergraph.compression_min_size = 1024;
+    let subgraph_service = HttpClientService::from_config(
+        "test",
+        &config,
+        &rustls::RootCertStore::empty(),
+        Http2Config::Http2Only,
+        Some(0),
+    )
+    .unwrap();
+
+    let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+    let response = subgraph_service
+        .oneshot(HttpRequest {
+            http_request: http::Request::builder()
+                .uri(url)
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .header(CONTENT_ENCODING, "gzip")
+                .body(r#"{"query":"{ me { name username } }"#.into())
+                .unwrap(),
+            context: Context::new(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::str::from_utf8(
+            &get_body_bytes(response.http_response.into_parts().1)
+                .await
+                .unwrap()
+        )
+        .unwrap(),
+        r#"{"data":"test"}"#
+    );
+}
+
 const SCHEMA: &str = include_str!("../../testdata/orga_supergraph.graphql");
 
 struct TestPlugin {